@@ -1,34 +1,187 @@
 use anyhow::bail;
-use clap::Parser;
-use sar_core::SymbolArtDrawer;
+use clap::{Parser, ValueEnum};
+use glob::Pattern;
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
 use sar_core::renderer::draw::Drawer;
-use std::{io::Cursor, path::Path, sync::Arc};
-use tokio::{fs, task::spawn_blocking};
+use sar_core::{Color, Payload, SymbolArt, SymbolArtDrawer, SymbolArtLayer, SymbolId};
+use serde::Serialize;
+use std::{
+    io::{Cursor, Read},
+    num::NonZeroUsize,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+use tokio::{
+    fs,
+    io::AsyncWriteExt,
+    sync::{Mutex, Semaphore},
+    task::{JoinSet, spawn_blocking},
+};
 use tokio_stream::{StreamExt, wrappers::ReadDirStream};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Path to the SAR file or directory
+    /// Path to the SAR file or directory, or - to read a SAR file from stdin
     #[arg(short, long)]
     input: String,
-    /// Path to the output directory
-    #[arg(short, long)]
-    output: String,
+    /// Path to the output directory, or - to write the rendered image to stdout
+    #[arg(short, long, required_unless_present = "check")]
+    output: Option<String>,
     /// Raise errors instead of ignoring them
     #[arg(long, default_value_t = false)]
     raise_error: bool,
     /// Overwrite existing files
     #[arg(long, default_value_t = false)]
     overwrite: bool,
+    /// Skip files whose output is newer than the input, and render the others even without
+    /// --overwrite
+    #[arg(long, default_value_t = false)]
+    skip_unchanged: bool,
+    /// Scale factor of the rendered images
+    #[arg(long, default_value_t = 1.0)]
+    scale: f32,
+    /// Only render files in the input directory whose name matches the glob pattern
+    #[arg(long)]
+    pattern: Option<Pattern>,
+    /// Format of the rendered images
+    #[arg(long, value_enum, default_value_t = Format::Png)]
+    format: Format,
+    /// Background color of the rendered images as #RRGGBB or #RRGGBBAA [default: transparent, white for jpeg]
+    #[arg(long, value_parser = parse_color)]
+    background: Option<Rgba<u8>>,
+    /// Number of files rendered concurrently [default: number of CPUs]
+    #[arg(short, long)]
+    jobs: Option<NonZeroUsize>,
+    /// Show the number of processed files while rendering a directory, skipped and failed ones
+    /// included
+    #[arg(long, default_value_t = false)]
+    progress: bool,
+    /// Name of the rendered images, relative to the output directory. {name} expands to the input
+    /// file name, {stem} to the file name without its extension, and {ext} to the format extension
+    #[arg(long, default_value = "{name}.{ext}")]
+    name_template: String,
+    /// Name the rendered images without the extension of the input, like --name-template
+    /// {stem}.{ext}
+    #[arg(long, default_value_t = false, conflicts_with = "name_template")]
+    strip_extension: bool,
+    /// Write the rendered images under the directories of their input path, so that
+    /// arts/team/a.sar is written to <OUTPUT>/arts/team/. Absolute paths are kept without their root
+    #[arg(long, default_value_t = false)]
+    mirror_tree: bool,
+    /// Print the files that would be rendered as `input -> output` without reading or writing them
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+    /// Write a JSON array describing every rendered or failed file to the given path
+    #[arg(long)]
+    manifest: Option<String>,
+    /// Only parse the input files and report the malformed ones, exiting with an error if any
+    #[arg(long, default_value_t = false)]
+    check: bool,
+    /// Reject files declaring more layers than they hold or using unknown symbols, instead of
+    /// rendering what can be read
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+    /// Write every visible layer of a file as its own image instead, into a directory named like
+    /// the image would be. The images are named {index}-{symbol}.{ext}, the index starting from
+    /// the top layer
+    #[arg(long, default_value_t = false)]
+    split_layers: bool,
+}
+
+/// Path standing for stdin as the input and stdout as the output
+const STDIO: &str = "-";
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Format {
+    Png,
+    Jpeg,
+    Webp,
+    Bmp,
+}
+
+impl Format {
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Format::Png => ImageFormat::Png,
+            Format::Jpeg => ImageFormat::Jpeg,
+            Format::Webp => ImageFormat::WebP,
+            Format::Bmp => ImageFormat::Bmp,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Jpeg => "jpg",
+            Format::Webp => "webp",
+            Format::Bmp => "bmp",
+        }
+    }
+
+    /// Whether the format can store the alpha channel
+    fn supports_alpha(self) -> bool {
+        self != Format::Jpeg
+    }
+}
+
+fn parse_color(value: &str) -> Result<Rgba<u8>, String> {
+    Color::from_hex(value)
+        .map(Rgba::from)
+        .map_err(|e| e.to_string())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     let args = Args::parse();
 
+    if !(args.scale.is_finite() && args.scale > 0.0) {
+        bail!("scale must be a positive number: {}", args.scale)
+    }
+
+    let mut drawer = Draw::new(Arc::new(build_drawer(&args)), &args);
+    if let Some(path) = args
+        .manifest
+        .as_deref()
+        .filter(|_| !args.dry_run && !args.check)
+    {
+        drawer = drawer.with_manifest(Manifest::create(Path::new(path)).await?);
+    }
+
+    // The manifest is closed even when rendering fails, so that it stays valid JSON
+    let result = run(&drawer, &args).await;
+    drawer.finish_manifest().await?;
+    result
+}
+
+async fn run(drawer: &Draw, args: &Args) -> anyhow::Result<()> {
     let input = std::path::Path::new(&args.input);
-    let output = std::path::Path::new(&args.output);
+    if args.check {
+        return if input.is_dir() {
+            drawer.check_dir(input).await
+        } else {
+            drawer
+                .check_parse(input)
+                .await
+                .map_err(|e| anyhow::anyhow!("{}: {e}", input.to_string_lossy()))
+        };
+    }
+
+    let Some(output_arg) = args.output.as_deref() else {
+        bail!("--output is required unless --check is given")
+    };
+    let output = std::path::Path::new(output_arg);
+    if output_arg == STDIO {
+        if args.split_layers {
+            bail!("--split-layers writes a directory per file and can't write to stdout")
+        }
+        let result = drawer.draw_stdio(input).await;
+        drawer.record(input, Path::new(STDIO), &result).await?;
+        return result.map(|_| ());
+    }
+    if args.input == STDIO {
+        bail!("reading from stdin requires writing to stdout with --output -")
+    }
 
     if output.is_file() {
         bail!("output_path already exists: {}", output.to_string_lossy())
@@ -39,38 +192,307 @@ async fn main() -> Result<(), anyhow::Error> {
             output.to_string_lossy()
         )
     }
-    if !output.exists() {
+    if !output.exists() && !args.dry_run {
         fs::create_dir(output).await?;
     }
 
-    let drawer = Draw::new(
-        Arc::new(sar_core::SymbolArtDrawer::new().with_raise_error(args.raise_error)),
-        args.overwrite,
-    );
     if input.is_dir() {
         drawer.draw_dir(input, output).await
     } else {
-        let output = output.join(format!(
-            "{}.png",
-            input.file_name().unwrap().to_string_lossy()
-        ));
-        drawer.draw_file(input, &output).await
+        let output = output.join(drawer.output_file_name(input));
+        if drawer.is_unchanged(input, &output) {
+            eprintln!(
+                "skipped, output is up to date: {}",
+                output.to_string_lossy()
+            );
+            return Ok(());
+        }
+        let result = drawer.draw_file(input, &output).await;
+        drawer.record(input, &output, &result).await?;
+        result.map(|_| ())
+    }
+}
+
+fn build_drawer(args: &Args) -> SymbolArtDrawer {
+    let mut drawer = SymbolArtDrawer::new().with_raise_error(args.raise_error);
+    let background = match args.background {
+        // Formats without alpha can't show through to a transparent background
+        Some(Rgba([r, g, b, _])) if !args.format.supports_alpha() => Some(Rgba([r, g, b, 255])),
+        None if !args.format.supports_alpha() => Some(Rgba([255; 4])),
+        background => background,
+    };
+    if let Some(background) = background {
+        drawer = drawer.with_background(background);
+    }
+    drawer
+}
+
+/// Reads and parses the SAR file at `input_file`, or stdin when it is `-`
+///
+/// With `strict`, malformed files are rejected with `sar_core::parse_strict`.
+fn parse_input(input_file: &Path, strict: bool) -> sar_core::Result<Payload> {
+    let stdin = input_file == Path::new(STDIO);
+    if !strict {
+        return if stdin {
+            sar_core::parse_reader(std::io::stdin())
+        } else {
+            sar_core::parse_file(input_file)
+        };
+    }
+
+    let bytes = if stdin {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes)?;
+        bytes
+    } else {
+        std::fs::read(input_file)?
+    };
+    sar_core::parse_strict(bytes)
+}
+
+/// Counts of the files processed while rendering a directory
+#[derive(Debug, Default, PartialEq)]
+struct Summary {
+    rendered: usize,
+    skipped: usize,
+    failed: usize,
+}
+
+impl Summary {
+    fn processed(&self) -> usize {
+        self.rendered + self.skipped + self.failed
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "rendered: {}, skipped: {}, failed: {}",
+            self.rendered, self.skipped, self.failed
+        )
     }
 }
 
+/// What is known about a rendered file, for the manifest
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rendered {
+    width: u32,
+    height: u32,
+    layers: usize,
+}
+
+/// An entry of the manifest, for a file that was rendered or failed to render
+#[derive(Debug, Serialize)]
+struct ManifestEntry {
+    input: String,
+    output: String,
+    width: Option<u32>,
+    height: Option<u32>,
+    layers: Option<usize>,
+    error: Option<String>,
+}
+
+/// Writes the manifest as a JSON array, one entry at a time as the files are rendered
+struct Manifest {
+    file: fs::File,
+    entries: usize,
+}
+
+impl Manifest {
+    async fn create(path: &Path) -> anyhow::Result<Self> {
+        let mut file = fs::File::create(path).await?;
+        file.write_all(b"[").await?;
+        Ok(Self { file, entries: 0 })
+    }
+
+    async fn push(&mut self, entry: &ManifestEntry) -> anyhow::Result<()> {
+        let separator = if self.entries == 0 { "\n  " } else { ",\n  " };
+        let line = format!("{separator}{}", serde_json::to_string(entry)?);
+        self.file.write_all(line.as_bytes()).await?;
+        self.file.flush().await?;
+        self.entries += 1;
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> anyhow::Result<()> {
+        let end = if self.entries == 0 { "]\n" } else { "\n]\n" };
+        self.file.write_all(end.as_bytes()).await?;
+        self.file.flush().await?;
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
 struct Draw {
     drawer: Arc<SymbolArtDrawer>,
+    raise_error: bool,
     overwrite: bool,
+    skip_unchanged: bool,
+    scale: f32,
+    pattern: Option<Pattern>,
+    format: Format,
+    jobs: usize,
+    progress: bool,
+    name_template: String,
+    mirror_tree: bool,
+    dry_run: bool,
+    strict: bool,
+    split_layers: bool,
+    manifest: Option<Arc<Mutex<Manifest>>>,
 }
 
 impl Draw {
-    fn new(drawer: Arc<SymbolArtDrawer>, overwrite: bool) -> Self {
-        Self { drawer, overwrite }
+    fn new(drawer: Arc<SymbolArtDrawer>, args: &Args) -> Self {
+        Self {
+            drawer,
+            raise_error: args.raise_error,
+            overwrite: args.overwrite,
+            skip_unchanged: args.skip_unchanged,
+            scale: args.scale,
+            pattern: args.pattern.clone(),
+            format: args.format,
+            jobs: args
+                .jobs
+                .or_else(|| std::thread::available_parallelism().ok())
+                .map_or(1, NonZeroUsize::get),
+            progress: args.progress,
+            name_template: if args.strip_extension {
+                "{stem}.{ext}".to_string()
+            } else {
+                args.name_template.clone()
+            },
+            mirror_tree: args.mirror_tree,
+            dry_run: args.dry_run,
+            strict: args.strict,
+            split_layers: args.split_layers,
+            manifest: None,
+        }
+    }
+
+    fn with_manifest(mut self, manifest: Manifest) -> Self {
+        self.manifest = Some(Arc::new(Mutex::new(manifest)));
+        self
+    }
+
+    /// Adds the outcome of rendering `input_file` to the manifest, if there is one
+    async fn record(
+        &self,
+        input_file: &Path,
+        output_file: &Path,
+        result: &anyhow::Result<Option<Rendered>>,
+    ) -> anyhow::Result<()> {
+        let Some(manifest) = &self.manifest else {
+            return Ok(());
+        };
+
+        let (rendered, error) = match result {
+            Ok(rendered) => (*rendered, None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let entry = ManifestEntry {
+            input: input_file.to_string_lossy().into_owned(),
+            output: output_file.to_string_lossy().into_owned(),
+            width: rendered.map(|r| r.width),
+            height: rendered.map(|r| r.height),
+            layers: rendered.map(|r| r.layers),
+            error,
+        };
+        manifest.lock().await.push(&entry).await
+    }
+
+    async fn finish_manifest(&self) -> anyhow::Result<()> {
+        match &self.manifest {
+            Some(manifest) => manifest.lock().await.finish().await,
+            None => Ok(()),
+        }
+    }
+
+    /// The output path of `input_file` relative to the output directory
+    ///
+    /// With `--mirror-tree` the directories of `input_file` come first, leaving
+    /// out the root and `..` so that the output stays in the output directory.
+    /// With `--split-layers` this is the directory the layers are written to,
+    /// named like the image without its extension.
+    fn output_file_name(&self, input_file: &Path) -> String {
+        let name = input_file.file_name().unwrap().to_string_lossy();
+        let stem = input_file.file_stem().unwrap().to_string_lossy();
+        let mut output = self
+            .name_template
+            .replace("{name}", &name)
+            .replace("{stem}", &stem)
+            .replace("{ext}", self.format.extension());
+        if self.mirror_tree {
+            let prefix = input_file
+                .parent()
+                .into_iter()
+                .flat_map(Path::components)
+                .filter(|component| matches!(component, Component::Normal(_)))
+                .collect::<PathBuf>();
+            output = prefix.join(output).to_string_lossy().into_owned();
+        }
+        if !self.split_layers {
+            return output;
+        }
+
+        let extension = format!(".{}", self.format.extension());
+        output
+            .strip_suffix(&extension)
+            .map_or(output.clone(), str::to_string)
+    }
+
+    /// The name of the image of a layer written with `--split-layers`
+    fn layer_file_name(&self, index: usize, symbol: SymbolId) -> String {
+        format!("{index:03}-{}.{}", symbol.id(), self.format.extension())
+    }
+
+    fn encode(&self, image: RgbaImage) -> anyhow::Result<Vec<u8>> {
+        let image = if self.format.supports_alpha() {
+            DynamicImage::ImageRgba8(image)
+        } else {
+            DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(image).to_rgb8())
+        };
+
+        let mut cursor = Cursor::new(Vec::new());
+        image.write_to(&mut cursor, self.format.image_format())?;
+        Ok(cursor.into_inner())
+    }
+
+    /// Whether existing outputs may be replaced, which `--skip-unchanged` allows for stale ones
+    fn can_overwrite(&self) -> bool {
+        self.overwrite || self.skip_unchanged
+    }
+
+    /// Whether `--skip-unchanged` is set and the output is newer than the input
+    fn is_unchanged(&self, input_file: &Path, output_file: &Path) -> bool {
+        if !self.skip_unchanged {
+            return false;
+        }
+
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        match (modified(input_file), modified(output_file)) {
+            (Some(input), Some(output)) => output >= input,
+            _ => false,
+        }
+    }
+
+    fn matches_pattern(&self, path: &Path) -> bool {
+        let Some(pattern) = &self.pattern else {
+            return true;
+        };
+
+        path.file_name()
+            .is_some_and(|name| pattern.matches(&name.to_string_lossy()))
     }
 }
 
 impl Draw {
-    async fn draw_dir(&self, input_dir: &Path, output_dir: &Path) -> Result<(), anyhow::Error> {
+    /// The SAR files of the input directory matching `--pattern`, skipping directories and symlinks
+    ///
+    /// Other files lying next to the SAR files, like a README, are left out
+    /// silently like those not matching `--pattern`, rather than failing.
+    async fn input_files(&self, input_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let mut input_files = Vec::new();
         let mut stream = ReadDirStream::new(tokio::fs::read_dir(input_dir).await?);
         while let Some(entry) = stream.next().await {
             let entry = entry?;
@@ -78,55 +500,660 @@ impl Draw {
             if input_path.is_dir() || input_path.is_symlink() {
                 continue;
             }
+            if !Self::has_sar_extension(&input_path) || !self.matches_pattern(&input_path) {
+                continue;
+            }
+
+            input_files.push(input_path);
+        }
+
+        Ok(input_files)
+    }
+
+    async fn draw_dir(&self, input_dir: &Path, output_dir: &Path) -> Result<(), anyhow::Error> {
+        let input_files = self.input_files(input_dir).await?;
+        let total = input_files.len();
+        let mut summary = Summary::default();
+        let semaphore = Arc::new(Semaphore::new(self.jobs));
+        let mut tasks = JoinSet::new();
+        for input_path in input_files {
+            let output_file = output_dir.join(self.output_file_name(&input_path));
+            if self.is_unchanged(&input_path, &output_file)
+                || (output_file.exists() && !self.can_overwrite())
+            {
+                summary.skipped += 1;
+                self.print_progress(&summary, total);
+                continue;
+            }
+            if self.dry_run {
+                match self.check_file(&input_path, &output_file) {
+                    Ok(()) => Self::print_dry_run(&input_path, &output_file),
+                    Err(e) if self.raise_error => return Err(e),
+                    Err(e) => eprintln!("failed to render: {}: {e}", input_path.to_string_lossy()),
+                }
+                continue;
+            }
 
-            let output_file = output_dir.join(format!(
-                "{}.png",
-                input_path.file_name().unwrap().to_string_lossy()
-            ));
+            let semaphore = semaphore.clone();
+            let draw = self.clone();
+            tasks.spawn(async move {
+                let result = match semaphore.acquire().await {
+                    Ok(_permit) => draw.draw_file(&input_path, &output_file).await,
+                    Err(e) => Err(e.into()),
+                };
+                (input_path, output_file, result)
+            });
+        }
 
-            let _ = self
-                .draw_file(&input_path, &output_file)
-                .await
-                .inspect_err(|e| {
-                    eprintln!("failed to render: {}: {}", input_path.to_string_lossy(), e)
-                });
+        if self.dry_run {
+            return Ok(());
+        }
+        while let Some(result) = tasks.join_next().await {
+            let (input_path, output_file, result) = result?;
+            self.record(&input_path, &output_file, &result).await?;
+            match result.map_err(|e| anyhow::anyhow!("{}: {}", input_path.to_string_lossy(), e)) {
+                Ok(_) => summary.rendered += 1,
+                Err(e) if self.raise_error => return Err(e),
+                Err(e) => {
+                    summary.failed += 1;
+                    if self.progress {
+                        eprint!("\r");
+                    }
+                    eprintln!("failed to render: {e}");
+                }
+            }
+            self.print_progress(&summary, total);
+        }
+        if self.progress {
+            eprintln!();
+        }
+        println!("{summary}");
+
+        Ok(())
+    }
+
+    /// Parses every file of the input directory, failing if any of them is malformed
+    async fn check_dir(&self, input_dir: &Path) -> anyhow::Result<()> {
+        let input_files = self.input_files(input_dir).await?;
+        let total = input_files.len();
+        let semaphore = Arc::new(Semaphore::new(self.jobs));
+        let mut tasks = JoinSet::new();
+        for input_path in input_files {
+            let semaphore = semaphore.clone();
+            let draw = self.clone();
+            tasks.spawn(async move {
+                let result = match semaphore.acquire().await {
+                    Ok(_permit) => draw.check_parse(&input_path).await,
+                    Err(e) => Err(e.into()),
+                };
+                (input_path, result)
+            });
+        }
+
+        let mut failed = 0;
+        while let Some(result) = tasks.join_next().await {
+            let (input_path, result) = result?;
+            match result.map_err(|e| anyhow::anyhow!("{}: {}", input_path.to_string_lossy(), e)) {
+                Ok(()) => {}
+                Err(e) if self.raise_error => return Err(e),
+                Err(e) => {
+                    failed += 1;
+                    eprintln!("invalid: {e}");
+                }
+            }
+        }
+        println!("checked: {total}, failed: {failed}");
+
+        if failed > 0 {
+            bail!("{failed} of {total} files failed the check")
+        }
+        Ok(())
+    }
+
+    /// Parses a SAR file, or stdin when the input is `-`, without rendering it
+    async fn check_parse(&self, input_file: &Path) -> anyhow::Result<()> {
+        if input_file != Path::new(STDIO) {
+            Self::check_input(input_file)?;
         }
 
+        let (input_file, strict) = (input_file.to_path_buf(), self.strict);
+        spawn_blocking(move || parse_input(&input_file, strict)).await??;
         Ok(())
     }
 
-    async fn draw_file(&self, input_file: &Path, output_file: &Path) -> anyhow::Result<()> {
+    /// Renders a SAR file to `output_file`, returning `None` in a dry run
+    async fn draw_file(
+        &self,
+        input_file: &Path,
+        output_file: &Path,
+    ) -> anyhow::Result<Option<Rendered>> {
+        self.check_file(input_file, output_file)?;
+        if self.dry_run {
+            Self::print_dry_run(input_file, output_file);
+            return Ok(None);
+        }
+
+        if self.split_layers {
+            return self.draw_layers(input_file, output_file).await.map(Some);
+        }
+        if let Some(parent) = output_file.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let (input_file, strict) = (input_file.to_path_buf(), self.strict);
+        let (bytes, rendered) = self
+            .render(move || parse_input(&input_file, strict))
+            .await?;
+        tokio::fs::write(output_file, bytes).await?;
+
+        Ok(Some(rendered))
+    }
+
+    /// Renders every visible layer of a SAR file as its own image into `output_dir`
+    ///
+    /// A layer that can't be drawn fails the file with `--raise-error`, and is
+    /// left out otherwise.
+    async fn draw_layers(&self, input_file: &Path, output_dir: &Path) -> anyhow::Result<Rendered> {
+        let drawer = self.drawer.clone();
+        let (input_file, strict) = (input_file.to_path_buf(), self.strict);
+        let (scale, raise_error) = (self.scale, self.raise_error);
+        let (images, layers) = spawn_blocking(move || {
+            let parsed = parse_input(&input_file, strict)?;
+            let layers = parsed.layers();
+            let mut images = Vec::new();
+            for (index, layer) in layers.iter().enumerate() {
+                if layer.is_hidden() {
+                    continue;
+                }
+                match drawer.draw_single_layer_with_scale(&parsed, index, scale) {
+                    Ok(image) => images.push((index, layer.symbol().id(), image)),
+                    Err(e) if raise_error => return Err(e),
+                    Err(_) => continue,
+                }
+            }
+            sar_core::Result::Ok((images, layers.len()))
+        })
+        .await??;
+
+        fs::create_dir_all(output_dir).await?;
+        let (mut width, mut height) = (0, 0);
+        for (index, symbol, image) in images {
+            (width, height) = image.dimensions();
+            let output_file = output_dir.join(self.layer_file_name(index, symbol));
+            tokio::fs::write(output_file, self.encode(image)?).await?;
+        }
+
+        Ok(Rendered {
+            width,
+            height,
+            layers,
+        })
+    }
+
+    /// Fails if the input isn't a SAR file or the output can't be written
+    fn check_file(&self, input_file: &Path, output_file: &Path) -> anyhow::Result<()> {
+        Self::check_input(input_file)?;
+        if output_file.exists() && !self.can_overwrite() {
+            bail!(
+                "output_file already exists: {}",
+                output_file.to_string_lossy()
+            )
+        }
+
+        Ok(())
+    }
+
+    /// Fails if the input isn't a file with the `sar` extension
+    fn check_input(input_file: &Path) -> anyhow::Result<()> {
         if !input_file.is_file() {
             bail!("input_file not found: {}", input_file.to_string_lossy())
         }
-        if input_file
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .is_some_and(|ext| ext == ".sar")
-        {
+        if !Self::has_sar_extension(input_file) {
             bail!(
                 "input_file is not a sar file: {}",
                 input_file.to_string_lossy()
             )
         }
-        if output_file.exists() && !self.overwrite {
-            bail!(
-                "output_file already exists: {}",
-                output_file.to_string_lossy()
-            )
+
+        Ok(())
+    }
+
+    fn has_sar_extension(path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("sar"))
+    }
+
+    /// Rewrites the `--progress` line with the number of files processed so far
+    ///
+    /// Nothing is shown in a dry run, whose output lists the files instead.
+    fn print_progress(&self, summary: &Summary, total: usize) {
+        if self.progress && !self.dry_run {
+            eprint!("\rprocessed {}/{}", summary.processed(), total);
         }
+    }
 
-        let bytes = tokio::fs::read(input_file).await?;
-        let parsed = sar_core::parse(bytes)?;
+    fn print_dry_run(input_file: &Path, output_file: &Path) {
+        println!(
+            "{} -> {}",
+            input_file.to_string_lossy(),
+            output_file.to_string_lossy()
+        );
+    }
+
+    /// Renders a single SAR file to stdout, reading it from stdin when the input is `-`
+    async fn draw_stdio(&self, input_file: &Path) -> anyhow::Result<Option<Rendered>> {
+        if input_file != Path::new(STDIO) {
+            Self::check_input(input_file)?;
+        }
+        if self.dry_run {
+            Self::print_dry_run(input_file, Path::new(STDIO));
+            return Ok(None);
+        }
 
+        let (input_file, strict) = (input_file.to_path_buf(), self.strict);
+        let (bytes, rendered) = self
+            .render(move || parse_input(&input_file, strict))
+            .await?;
+
+        let mut stdout = tokio::io::stdout();
+        stdout.write_all(&bytes).await?;
+        stdout.flush().await?;
+
+        Ok(Some(rendered))
+    }
+
+    /// Parses a SAR file with `parse` on a blocking thread and encodes the rendered image
+    async fn render<F>(&self, parse: F) -> anyhow::Result<(Vec<u8>, Rendered)>
+    where
+        F: FnOnce() -> sar_core::Result<Payload> + Send + 'static,
+    {
         let drawer = self.drawer.clone();
-        let image = spawn_blocking(move || drawer.draw(&parsed)).await??;
+        let scale = self.scale;
+        let (image, layers) = spawn_blocking(move || {
+            let parsed = parse()?;
+            let image = drawer.draw_with_scale(&parsed, scale)?;
+            sar_core::Result::Ok((image, parsed.layers().len()))
+        })
+        .await??;
 
-        let mut cursor = Cursor::new(Vec::new());
-        image.write_to(&mut cursor, image::ImageFormat::Png)?;
+        let rendered = Rendered {
+            width: image.width(),
+            height: image.height(),
+            layers,
+        };
+        Ok((self.encode(image)?, rendered))
+    }
+}
 
-        tokio::fs::write(output_file, cursor.into_inner()).await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok(())
+    fn args(extra: &[&str]) -> Args {
+        let args = ["sar-cli", "--input", "input", "--output", "output"];
+        Args::parse_from(args.iter().chain(extra))
+    }
+
+    #[tokio::test]
+    async fn test_draw_file_rejects_non_sar_file() {
+        let dir = std::env::temp_dir().join(format!("sar-cli-ext-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input.png");
+        std::fs::write(&input, b"not a sar file").unwrap();
+
+        let draw = Draw::new(Arc::new(SymbolArtDrawer::new()), &args(&[]));
+        let result = draw.draw_file(&input, &dir.join("output.png")).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("input_file is not a sar file"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn test_draw_stdio_rejects_non_sar_file() {
+        let dir = std::env::temp_dir().join(format!("sar-cli-stdio-ext-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("file.txt");
+        std::fs::write(&input, b"not a sar file").unwrap();
+
+        let draw = Draw::new(Arc::new(SymbolArtDrawer::new()), &args(&[]));
+        let result = draw.draw_stdio(&input).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("input_file is not a sar file"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn test_render() {
+        let input = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../fixture/sa005c2a2b46e647bafb3edb7effda6d5c.sar");
+        let draw = Draw::new(Arc::new(SymbolArtDrawer::new()), &args(&[]));
+
+        let (bytes, rendered) = draw
+            .render(move || sar_core::parse_file(input))
+            .await
+            .unwrap();
+
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Png);
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert_eq!(
+            (rendered.width, rendered.height),
+            (image.width(), image.height())
+        );
+        assert!(rendered.layers > 0);
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        let draw = Draw::new(
+            Arc::new(SymbolArtDrawer::new()),
+            &args(&["--pattern", "sa0*.sar"]),
+        );
+
+        assert!(draw.matches_pattern(Path::new("dir/sa0a1d.sar")));
+        assert!(!draw.matches_pattern(Path::new("dir/sa1a1d.sar")));
+        assert!(!draw.matches_pattern(Path::new("dir/sa0a1d.png")));
+    }
+
+    #[test]
+    fn test_output_file_name() {
+        let draw = Draw::new(Arc::new(SymbolArtDrawer::new()), &args(&[]));
+        assert_eq!(
+            draw.output_file_name(Path::new("dir/foo.sar")),
+            "foo.sar.png"
+        );
+
+        let draw = Draw::new(
+            Arc::new(SymbolArtDrawer::new()),
+            &args(&["--name-template", "art/{stem}.{ext}"]),
+        );
+        assert_eq!(
+            draw.output_file_name(Path::new("dir/foo.sar")),
+            "art/foo.png"
+        );
+
+        let draw = Draw::new(
+            Arc::new(SymbolArtDrawer::new()),
+            &args(&["--strip-extension", "--mirror-tree"]),
+        );
+        assert_eq!(
+            draw.output_file_name(Path::new("arts/team/foo.sar")),
+            "arts/team/foo.png"
+        );
+        assert_eq!(
+            draw.output_file_name(Path::new("/srv/../arts/foo.sar")),
+            "srv/arts/foo.png"
+        );
+        assert_eq!(draw.output_file_name(Path::new("foo.sar")), "foo.png");
+        assert!(
+            Args::try_parse_from([
+                "sar-cli",
+                "--input",
+                "input",
+                "--output",
+                "output",
+                "--strip-extension",
+                "--name-template",
+                "{name}",
+            ])
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mirror_tree() {
+        let dir = std::env::temp_dir().join(format!("sar-cli-mirror-{}", std::process::id()));
+        let (input_dir, output_dir) = (dir.join("input/team"), dir.join("output"));
+        std::fs::create_dir_all(&input_dir).unwrap();
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../fixture/sa005c2a2b46e647bafb3edb7effda6d5c.sar");
+        std::fs::copy(&fixture, input_dir.join("art.sar")).unwrap();
+
+        let draw = Draw::new(
+            Arc::new(SymbolArtDrawer::new()),
+            &args(&["--mirror-tree", "--strip-extension"]),
+        );
+        let result = draw.draw_dir(&input_dir, &output_dir).await;
+        // The temporary input directory is absolute, so it is mirrored without its root
+        let mirrored = input_dir
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .collect::<PathBuf>();
+        let written = std::fs::read(output_dir.join(mirrored).join("art.png"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        result.unwrap();
+        assert_eq!(
+            image::guess_format(&written.unwrap()).unwrap(),
+            ImageFormat::Png
+        );
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#ff8000"), Ok(Rgba([255, 128, 0, 255])));
+        assert_eq!(parse_color("ff800080"), Ok(Rgba([255, 128, 0, 128])));
+        assert!(parse_color("#ff80").is_err());
+        assert!(parse_color("#gg8000").is_err());
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = Summary {
+            rendered: 3,
+            skipped: 2,
+            failed: 1,
+        };
+
+        assert_eq!(summary.processed(), 6);
+        assert_eq!(summary.to_string(), "rendered: 3, skipped: 2, failed: 1");
+    }
+
+    #[test]
+    fn test_encode_jpeg() {
+        let draw = Draw::new(
+            Arc::new(SymbolArtDrawer::new()),
+            &args(&["--format", "jpeg"]),
+        );
+        let image = RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255]));
+
+        let bytes = draw.encode(image).unwrap();
+        let format = image::guess_format(&bytes).unwrap();
+
+        assert_eq!(format, ImageFormat::Jpeg);
+        assert_eq!(draw.output_file_name(Path::new("a.sar")), "a.sar.jpg");
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_writes_nothing() {
+        let dir = std::env::temp_dir().join(format!("sar-cli-dry-run-{}", std::process::id()));
+        let (input_dir, output_dir) = (dir.join("input"), dir.join("output"));
+        std::fs::create_dir_all(&input_dir).unwrap();
+        std::fs::create_dir_all(&output_dir).unwrap();
+        // The content isn't a SAR file, which a dry run never finds out
+        std::fs::write(input_dir.join("a.sar"), b"not parsed").unwrap();
+        std::fs::write(input_dir.join("b.sar"), b"not parsed").unwrap();
+        std::fs::write(output_dir.join("b.sar.png"), b"existing").unwrap();
+
+        let draw = Draw::new(
+            Arc::new(SymbolArtDrawer::new()),
+            &args(&["--dry-run", "--name-template", "art/{name}.{ext}"]),
+        );
+        let result = draw.draw_dir(&input_dir, &output_dir).await;
+        let file_result = draw
+            .draw_file(&input_dir.join("a.sar"), &output_dir.join("a.png"))
+            .await;
+        let written = std::fs::read_dir(&output_dir).unwrap().count();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        result.unwrap();
+        file_result.unwrap();
+        assert_eq!(written, 1);
+    }
+
+    #[tokio::test]
+    async fn test_skip_unchanged() {
+        let dir = std::env::temp_dir().join(format!("sar-cli-unchanged-{}", std::process::id()));
+        let (input_dir, output_dir) = (dir.join("input"), dir.join("output"));
+        std::fs::create_dir_all(&input_dir).unwrap();
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../fixture/sa005c2a2b46e647bafb3edb7effda6d5c.sar");
+        for name in ["fresh", "stale"] {
+            let input = input_dir.join(format!("{name}.sar"));
+            std::fs::copy(&fixture, &input).unwrap();
+            std::fs::write(output_dir.join(format!("{name}.sar.png")), b"old").unwrap();
+        }
+        // Make the output of the stale file older than its input
+        let stale = std::fs::File::options()
+            .write(true)
+            .open(output_dir.join("stale.sar.png"))
+            .unwrap();
+        stale
+            .set_modified(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap();
+
+        let draw = Draw::new(
+            Arc::new(SymbolArtDrawer::new()),
+            &args(&["--skip-unchanged"]),
+        );
+        let result = draw.draw_dir(&input_dir, &output_dir).await;
+        let fresh = std::fs::read(output_dir.join("fresh.sar.png")).unwrap();
+        let stale = std::fs::read(output_dir.join("stale.sar.png")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        result.unwrap();
+        assert_eq!(fresh, b"old");
+        assert_eq!(image::guess_format(&stale).unwrap(), ImageFormat::Png);
+    }
+
+    #[tokio::test]
+    async fn test_manifest() {
+        let dir = std::env::temp_dir().join(format!("sar-cli-manifest-{}", std::process::id()));
+        let (input_dir, output_dir) = (dir.join("input"), dir.join("output"));
+        std::fs::create_dir_all(&input_dir).unwrap();
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../fixture/sa005c2a2b46e647bafb3edb7effda6d5c.sar");
+        std::fs::copy(&fixture, input_dir.join("good.sar")).unwrap();
+        std::fs::write(input_dir.join("bad.sar"), b"not a sar file").unwrap();
+
+        let path = dir.join("manifest.json");
+        let draw = Draw::new(Arc::new(SymbolArtDrawer::new()), &args(&[]))
+            .with_manifest(Manifest::create(&path).await.unwrap());
+        draw.draw_dir(&input_dir, &output_dir).await.unwrap();
+        draw.finish_manifest().await.unwrap();
+        let manifest = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut entries: Vec<serde_json::Value> = serde_json::from_str(&manifest).unwrap();
+        entries.sort_by_key(|entry| entry["input"].as_str().unwrap().to_string());
+        assert_eq!(entries.len(), 2);
+
+        let (bad, good) = (&entries[0], &entries[1]);
+        assert!(bad["input"].as_str().unwrap().ends_with("bad.sar"));
+        assert!(bad["error"].is_string());
+        assert!(bad["width"].is_null());
+        assert!(good["output"].as_str().unwrap().ends_with("good.sar.png"));
+        assert!(good["error"].is_null());
+        assert!(good["width"].as_u64().unwrap() > 0);
+        assert!(good["layers"].as_u64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_split_layers() {
+        let dir = std::env::temp_dir().join(format!("sar-cli-split-{}", std::process::id()));
+        let (input_dir, output_dir) = (dir.join("input"), dir.join("output"));
+        std::fs::create_dir_all(&input_dir).unwrap();
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../fixture/sa005c2a2b46e647bafb3edb7effda6d5c.sar");
+        let input = input_dir.join("art.sar");
+        std::fs::copy(&fixture, &input).unwrap();
+
+        let draw = Draw::new(
+            Arc::new(SymbolArtDrawer::new()),
+            &args(&["--split-layers", "--scale", "0.5"]),
+        );
+        let output = output_dir.join(draw.output_file_name(&input));
+        let rendered = draw.draw_file(&input, &output).await;
+        let mut names = std::fs::read_dir(&output)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        let first = std::fs::read(output.join(&names[0])).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Layers that can't be drawn are left out without --raise-error
+        let sa = sar_core::parse_file(&fixture).unwrap();
+        let drawer = SymbolArtDrawer::new();
+        let visible = (0..sa.layers().len())
+            .filter(|&i| !sa.layers()[i].is_hidden())
+            .filter(|&i| drawer.draw_single_layer(&sa, i).is_ok())
+            .count();
+        let rendered = rendered.unwrap().unwrap();
+        assert_eq!(output.file_name().unwrap(), "art.sar");
+        assert_eq!((rendered.width, rendered.height), (96, 48));
+        assert_eq!(rendered.layers, sa.layers().len());
+        assert_eq!(names.len(), visible);
+        names.sort();
+        let layer = &sa.layers()[0];
+        assert_eq!(names[0], format!("000-{}.png", layer.symbol().id().id()));
+        assert_eq!(image::guess_format(&first).unwrap(), ImageFormat::Png);
+    }
+
+    #[tokio::test]
+    async fn test_draw_dir_skips_other_files() {
+        let dir = std::env::temp_dir().join(format!("sar-cli-other-{}", std::process::id()));
+        let (input_dir, output_dir) = (dir.join("input"), dir.join("output"));
+        std::fs::create_dir_all(&input_dir).unwrap();
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../fixture/sa005c2a2b46e647bafb3edb7effda6d5c.sar");
+        std::fs::copy(&fixture, input_dir.join("art.SAR")).unwrap();
+        std::fs::write(input_dir.join("README"), b"notes").unwrap();
+        std::fs::write(input_dir.join(".DS_Store"), b"finder").unwrap();
+
+        let draw = Draw::new(Arc::new(SymbolArtDrawer::new()), &args(&["--raise-error"]));
+        let result = draw.draw_dir(&input_dir, &output_dir).await;
+        let written = std::fs::read_dir(&output_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        result.unwrap();
+        assert_eq!(written, ["art.SAR.png"]);
+    }
+
+    #[tokio::test]
+    async fn test_check() {
+        let dir = std::env::temp_dir().join(format!("sar-cli-check-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../fixture/sa005c2a2b46e647bafb3edb7effda6d5c.sar");
+        std::fs::copy(&fixture, dir.join("good.sar")).unwrap();
+        std::fs::write(dir.join("README"), b"notes").unwrap();
+
+        let args = Args::parse_from(["sar-cli", "--input", "input", "--check", "--strict"]);
+        assert_eq!(args.output, None);
+        assert!(Args::try_parse_from(["sar-cli", "--input", "input"]).is_err());
+
+        let draw = Draw::new(Arc::new(SymbolArtDrawer::new()), &args);
+        let valid = draw.check_dir(&dir).await;
+        std::fs::write(dir.join("bad.sar"), b"not a sar file").unwrap();
+        let invalid = draw.check_dir(&dir).await;
+        let bad = draw.check_parse(&dir.join("bad.sar")).await;
+        let written = std::fs::read_dir(&dir).unwrap().count();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        valid.unwrap();
+        let error = invalid.unwrap_err().to_string();
+        assert_eq!(error, "1 of 2 files failed the check");
+        assert!(bad.is_err());
+        assert_eq!(written, 3);
     }
 }
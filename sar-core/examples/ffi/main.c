@@ -0,0 +1,55 @@
+// Draws a SAR file to a PNG file through the C bindings
+//
+// Build the static library with
+// `cargo rustc -p sar-core --lib --release --features ffi --crate-type staticlib`,
+// then from the repository root:
+//
+//   cc sar-core/examples/ffi/main.c -Isar-core/include -Ltarget/release -l:libsar_core.a -lm -o sar-draw
+//   ./sar-draw fixture/sa0a1d081b8a108bb8c9847c4cd83db662.sar out.png
+
+#include <stdio.h>
+
+#include "sar.h"
+
+static int fail(const char *step, SarErrorCode code) {
+  fprintf(stderr, "%s failed with %d: %s\n", step, code, sar_last_error_message());
+  return 1;
+}
+
+int main(int argc, char **argv) {
+  if (argc != 3) {
+    fprintf(stderr, "usage: %s <input.sar> <output.png>\n", argv[0]);
+    return 1;
+  }
+
+  FILE *input = fopen(argv[1], "rb");
+  if (!input) {
+    perror("fopen");
+    return 1;
+  }
+  uint8_t data[1 << 16];
+  size_t len = fread(data, 1, sizeof(data), input);
+  fclose(input);
+
+  SarHandle sa;
+  SarErrorCode code = sar_parse(data, len, &sa);
+  if (code != SAR_ERROR_CODE_OK) {
+    return fail("sar_parse", code);
+  }
+
+  uint8_t *png;
+  size_t png_len;
+  code = sar_draw(sa, 2.0f, &png, &png_len);
+  sar_free(sa);
+  if (code != SAR_ERROR_CODE_OK) {
+    return fail("sar_draw", code);
+  }
+
+  FILE *output = fopen(argv[2], "wb");
+  if (output) {
+    fwrite(png, 1, png_len, output);
+    fclose(output);
+  }
+  sar_free_bytes(png, png_len);
+  return output ? 0 : 1;
+}
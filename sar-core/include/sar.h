@@ -0,0 +1,98 @@
+#ifndef SAR_H
+#define SAR_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The status returned by the functions, zero on success
+ *
+ * Panics are caught and reported as `Panic` rather than unwinding into C.
+ */
+typedef enum SarErrorCode {
+  SAR_ERROR_CODE_OK = 0,
+  SAR_ERROR_CODE_NULL_POINTER = 1,
+  SAR_ERROR_CODE_PANIC = 2,
+  SAR_ERROR_CODE_INVALID_FILE_HEADER = 3,
+  SAR_ERROR_CODE_IO = 4,
+  SAR_ERROR_CODE_SYMBOL_NOT_FOUND = 5,
+  SAR_ERROR_CODE_IMAGE = 6,
+  SAR_ERROR_CODE_PROJECTION = 7,
+  SAR_ERROR_CODE_LAYER_OUT_OF_RANGE = 8,
+  SAR_ERROR_CODE_INVALID_RESOURCE = 9,
+  SAR_ERROR_CODE_INVALID_CANVAS_SIZE = 10,
+  SAR_ERROR_CODE_UNSUPPORTED_SIZE = 11,
+  SAR_ERROR_CODE_TOO_MANY_LAYERS = 12,
+  SAR_ERROR_CODE_TRUNCATED_LAYERS = 13,
+  SAR_ERROR_CODE_INVALID_LAYER_SYMBOL = 14,
+  SAR_ERROR_CODE_INVALID_HEX_COLOR = 15,
+  SAR_ERROR_CODE_CANCELLED = 16,
+} SarErrorCode;
+
+/**
+ * A parsed SymbolArt, only accessed through a `SarHandle`
+ */
+typedef struct SarSymbolArt SarSymbolArt;
+
+/**
+ * An opaque handle to a parsed SymbolArt, released with `sar_free`
+ */
+typedef struct SarSymbolArt *SarHandle;
+
+/**
+ * Parses the `len` bytes at `data` and stores a new handle in `out`
+ *
+ * On failure `out` is left untouched.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes and `out` to a writable handle.
+ */
+enum SarErrorCode sar_parse(const uint8_t *data, size_t len, SarHandle *out);
+
+/**
+ * Draws the SymbolArt at `scale` and stores the PNG bytes in `out` and `out_len`
+ *
+ * The bytes are released with `sar_free_bytes`. On failure `out` and
+ * `out_len` are left untouched.
+ *
+ * # Safety
+ *
+ * `handle` must come from `sar_parse` and not be freed yet, and `out` and
+ * `out_len` must be writable.
+ */
+enum SarErrorCode sar_draw(SarHandle handle, float scale, uint8_t **out, size_t *out_len);
+
+/**
+ * Releases a handle returned by `sar_parse`, doing nothing if it is null
+ *
+ * # Safety
+ *
+ * `handle` must come from `sar_parse` and not be freed yet.
+ */
+void sar_free(SarHandle handle);
+
+/**
+ * Releases the bytes returned by `sar_draw`, doing nothing if they are null
+ *
+ * # Safety
+ *
+ * `bytes` and `len` must be the values stored by `sar_draw`, and the bytes must
+ * not be freed yet.
+ */
+void sar_free_bytes(uint8_t *bytes, size_t len);
+
+/**
+ * The message of the last error on the calling thread, or null if none occurred
+ *
+ * The string is owned by the library and stays valid until the next error on
+ * the same thread.
+ */
+const char *sar_last_error_message(void);
+
+#endif  /* SAR_H */
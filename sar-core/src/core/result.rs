@@ -7,6 +7,12 @@ use super::symbol::SymbolId;
 /// `SARError` as the error type for all operations that can fail.
 pub type Result<T> = std::result::Result<T, SARError>;
 
+/// The errors returned by the parser and the renderer
+///
+/// `std::io::Error` and `image::ImageError` convert into `IoError` and
+/// `ImageError` with `?`, which display them unchanged. Reading, drawing and
+/// encoding a SymbolArt can all return this one type. `ImageError` only exists
+/// with the `render` feature.
 #[derive(thiserror::Error, Debug)]
 pub enum SARError {
     #[error("invalid file format")]
@@ -15,8 +21,27 @@ pub enum SARError {
     IoError(#[from] std::io::Error),
     #[error("symbol not found for id: {0}")]
     SymbolNotFound(SymbolId),
+    #[cfg(feature = "render")]
     #[error(transparent)]
     ImageError(#[from] image::ImageError),
-    #[error("failed to create projection for points: from {0:?} to {1:?}")]
-    ProjectionError([(f32, f32); 4], [(f32, f32); 4]),
+    #[error("failed to create projection for layer {0} (symbol {1}): from {2:?} to {3:?}")]
+    ProjectionError(usize, SymbolId, [(f32, f32); 4], [(f32, f32); 4]),
+    #[error("layer index {0} is out of range for {1} layers")]
+    LayerOutOfRange(usize, usize),
+    #[error("invalid symbol resource: {0}")]
+    InvalidResource(String),
+    #[error("invalid canvas size: {0}x{1}")]
+    InvalidCanvasSize(u32, u32),
+    #[error("unsupported SymbolArt size: {0}x{1}")]
+    UnsupportedSize(u8, u8),
+    #[error("{0} layers exceed the limit of {1} layers")]
+    TooManyLayers(usize, usize),
+    #[error("the header declares {0} layers but the file only holds {1}")]
+    TruncatedLayers(usize, usize),
+    #[error("layer {0} uses an unknown symbol id: {1}")]
+    InvalidLayerSymbol(usize, SymbolId),
+    #[error("invalid hex color, expected #RRGGBB or #RRGGBBAA: {0}")]
+    InvalidHexColor(String),
+    #[error("the render was cancelled")]
+    Cancelled,
 }
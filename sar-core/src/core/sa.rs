@@ -1,9 +1,22 @@
 use std::fmt::Debug;
 
-use super::symbol::Symbol;
+use super::{
+    result::{Result, SARError},
+    symbol::Symbol,
+};
+
+/// The number of layers PSO2 allows in a SymbolArt
+pub(crate) const MAX_LAYERS: usize = 225;
+
+/// How far apart the alphas of two layers may be for them to count as duplicates
+///
+/// `SymbolArtLayer::alpha` is a float the implementations compute, so the same
+/// opacity may come out a rounding error apart from two layers.
+const ALPHA_EPSILON: f32 = 1e-4;
 
 /// Represents a position in 2D space
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Position {
     /// X coordinate
     pub x: u8,
@@ -11,6 +24,47 @@ pub struct Position {
     pub y: u8,
 }
 
+/// Identifies the sound effect attached to a SymbolArt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct SoundId(u8);
+
+impl SoundId {
+    pub fn new(id: u8) -> Self {
+        Self(id)
+    }
+
+    pub fn id(&self) -> u8 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for SoundId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Summary metrics of a SymbolArt, returned by `SymbolArt::stats`
+///
+/// Everything but `layers` counts the visible layers only, like `palette` does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SaStats {
+    /// The number of layers, hidden ones included
+    pub layers: usize,
+    /// The number of layers that are drawn
+    pub visible_layers: usize,
+    /// The number of distinct symbols used
+    pub symbols: usize,
+    /// The number of distinct colors used
+    pub colors: usize,
+    /// The width and height of the box around the corners of the layers, zero
+    /// when no layer is visible
+    pub bounding_size: (u8, u8),
+}
+
 /// Represents a complete SymbolArt composition
 ///
 /// A SymbolArt is a user-created artwork composed of multiple layers of symbols.
@@ -21,7 +75,176 @@ pub trait SymbolArt: Send + Sync {
     fn height(&self) -> u8;
     fn width(&self) -> u8;
     fn layers(&self) -> Vec<Self::Layer>;
-    fn name(&self) -> String;
+    fn name(&self) -> &str;
+    /// The sound effect played when the SymbolArt is shown, if any
+    fn sound_effect(&self) -> Option<SoundId> {
+        None
+    }
+
+    /// The layers that are drawn, skipping the hidden ones like the renderer does
+    ///
+    /// The layers are yielded in the same order as `layers`, by value since
+    /// `layers` returns them owned. Reverse the iterator to walk them from the
+    /// bottom to the top.
+    ///
+    /// ```
+    /// use sar_core::{parse, SymbolArt, SymbolArtLayer};
+    ///
+    /// let bytes = include_bytes!("../../../fixture/sa0a1d081b8a108bb8c9847c4cd83db662.sar");
+    /// let sa = parse(Vec::from(bytes)).unwrap();
+    ///
+    /// for layer in sa.visible_layers() {
+    ///     assert!(!layer.is_hidden());
+    ///     println!("{} {:?}", layer.symbol().id(), layer.color());
+    /// }
+    /// assert!(sa.visible_layers().count() <= sa.layers().len());
+    /// ```
+    fn visible_layers(&self) -> impl DoubleEndedIterator<Item = Self::Layer> {
+        self.layers().into_iter().filter(|layer| !layer.is_hidden())
+    }
+
+    /// The distinct colors of the visible layers, in order of first appearance
+    fn palette(&self) -> Vec<Color> {
+        self.palette_with_counts()
+            .into_iter()
+            .map(|(color, _)| color)
+            .collect()
+    }
+
+    /// The distinct colors of the visible layers with the number of layers using each
+    fn palette_with_counts(&self) -> Vec<(Color, usize)> {
+        let mut palette: Vec<(Color, usize)> = Vec::new();
+        for layer in self.visible_layers() {
+            let color = layer.color();
+            match palette.iter_mut().find(|(known, _)| *known == color) {
+                Some((_, count)) => *count += 1,
+                None => palette.push((color, 1)),
+            }
+        }
+        palette
+    }
+
+    /// The layer, symbol and color counts and the extent of the SymbolArt
+    ///
+    /// ```
+    /// use sar_core::{parse, SymbolArt};
+    ///
+    /// let bytes = include_bytes!("../../../fixture/sa0a1d081b8a108bb8c9847c4cd83db662.sar");
+    /// let sa = parse(Vec::from(bytes)).unwrap();
+    ///
+    /// let stats = sa.stats();
+    /// assert_eq!(stats.layers, sa.layers().len());
+    /// assert!(stats.symbols <= stats.visible_layers);
+    /// ```
+    fn stats(&self) -> SaStats {
+        let layers = self.layers();
+        let visible = layers.iter().filter(|layer| !layer.is_hidden());
+
+        let mut symbols = Vec::new();
+        let mut bounds: Option<(Position, Position)> = None;
+        for layer in visible.clone() {
+            let id = layer.symbol().id();
+            if !symbols.contains(&id) {
+                symbols.push(id);
+            }
+            for p in layer.corners() {
+                let (min, max) = bounds.get_or_insert((p, p));
+                *min = Position {
+                    x: min.x.min(p.x),
+                    y: min.y.min(p.y),
+                };
+                *max = Position {
+                    x: max.x.max(p.x),
+                    y: max.y.max(p.y),
+                };
+            }
+        }
+
+        SaStats {
+            layers: layers.len(),
+            visible_layers: visible.count(),
+            symbols: symbols.len(),
+            colors: self.palette().len(),
+            bounding_size: bounds.map_or((0, 0), |(min, max)| (max.x - min.x, max.y - min.y)),
+        }
+    }
+
+    /// The index pairs `(i, j)` of layers that are identical, with `i < j`
+    ///
+    /// Two layers are identical when their symbol, color, corners, hidden state
+    /// and blend mode match, and their alphas differ by less than a tiny
+    /// epsilon. The corners are whole `u8` coordinates like in SAR files, so
+    /// they are compared exactly and need no tolerance, unlike the alpha which is
+    /// computed as a float. Both layers of a pair cover the same pixels with the
+    /// same color, so removing either leaves the image unchanged when they are
+    /// opaque. With several copies of a layer, every pair of them is returned.
+    fn find_duplicate_layers(&self) -> Vec<(usize, usize)> {
+        let layers = self.layers();
+        let mut duplicates = Vec::new();
+        for (i, a) in layers.iter().enumerate() {
+            for (j, b) in layers.iter().enumerate().skip(i + 1) {
+                if is_same_layer(a, b) {
+                    duplicates.push((i, j));
+                }
+            }
+        }
+        duplicates
+    }
+
+    /// A fingerprint of everything that changes how the SymbolArt is drawn
+    ///
+    /// The view size and, for every layer, the symbol, color, corners, alpha,
+    /// hidden state and blend mode are hashed with 64-bit FNV-1a, so the hash is
+    /// the same on every platform and across releases of Rust. The name, the
+    /// author and the sound effect are left out, so copies of an art renamed or
+    /// shared by someone else hash the same.
+    fn content_hash(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+
+        content_bytes(self)
+            .into_iter()
+            .fold(OFFSET_BASIS, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(PRIME)
+            })
+    }
+}
+
+/// Encodes what `SymbolArt::content_hash` hashes, in a fixed byte order
+pub(crate) fn content_bytes<S>(sa: &S) -> Vec<u8>
+where
+    S: SymbolArt + ?Sized,
+{
+    let layers = sa.layers();
+    let mut content = Vec::with_capacity(2 + layers.len() * 22);
+    content.extend([sa.width(), sa.height()]);
+    for layer in &layers {
+        let color = layer.color();
+        content.extend(layer.symbol().id().id().to_le_bytes());
+        content.extend([color.a, color.r, color.g, color.b]);
+        content.extend(layer.corners().into_iter().flat_map(|p| [p.x, p.y]));
+        content.extend(layer.alpha().to_bits().to_le_bytes());
+        let blend_mode = match layer.blend_mode() {
+            BlendMode::Normal => 0,
+            BlendMode::Multiply => 1,
+            BlendMode::Screen => 2,
+            BlendMode::Additive => 3,
+        };
+        content.extend([layer.is_hidden() as u8, blend_mode]);
+    }
+    content
+}
+
+fn is_same_layer<L>(a: &L, b: &L) -> bool
+where
+    L: SymbolArtLayer,
+{
+    a.symbol().id() == b.symbol().id()
+        && a.color() == b.color()
+        && a.corners() == b.corners()
+        && a.is_hidden() == b.is_hidden()
+        && a.blend_mode() == b.blend_mode()
+        && (a.alpha() - b.alpha()).abs() < ALPHA_EPSILON
 }
 
 /// Represents a single layer in a SymbolArt composition
@@ -35,9 +258,62 @@ pub trait SymbolArtLayer {
     fn bottom_left(&self) -> Position;
     fn top_right(&self) -> Position;
     fn bottom_right(&self) -> Position;
+    /// The corners clockwise from the top left: top-left, top-right,
+    /// bottom-right and bottom-left
+    ///
+    /// This is the order the symbol is projected onto the layer in, so its own
+    /// corners map to these in the same order.
+    fn corners(&self) -> [Position; 4] {
+        [
+            self.top_left(),
+            self.top_right(),
+            self.bottom_right(),
+            self.bottom_left(),
+        ]
+    }
     fn symbol(&self) -> Symbol;
     fn color(&self) -> Color;
     fn is_hidden(&self) -> bool;
+    /// The opacity of the whole layer, from 0.0 (invisible) to 1.0 (opaque)
+    ///
+    /// This scales the alpha of every pixel of the layer on top of the alpha
+    /// of its color. Defaults to 1.0.
+    fn alpha(&self) -> f32 {
+        1.0
+    }
+    /// How the layer is blended with the layers beneath it
+    ///
+    /// SAR files don't store a blend mode, so this defaults to `BlendMode::Normal`.
+    fn blend_mode(&self) -> BlendMode {
+        BlendMode::Normal
+    }
+    /// The color the renderer fills the symbol `image` of the layer with
+    ///
+    /// Textured symbols are filled with `color`, while symbols drawn with their
+    /// own colors return `None` as the layer color doesn't apply to them.
+    #[cfg(feature = "render")]
+    fn effective_color(&self, image: &crate::renderer::resource::Image<'_>) -> Option<Color> {
+        (!image.is_color_symbol()).then(|| self.color())
+    }
+}
+
+/// How the color of a layer is combined with the colors beneath it
+///
+/// The blended color is composited with the alpha of the layer, so a
+/// transparent pixel beneath a layer shows the layer color as is whatever the
+/// mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BlendMode {
+    /// Paints the layer over the ones beneath it
+    #[default]
+    Normal,
+    /// Multiplies the colors, which darkens them
+    Multiply,
+    /// Multiplies the inverted colors, which lightens them
+    Screen,
+    /// Adds the colors, clamped to white
+    Additive,
 }
 
 /// Represents a color in RGBA format
@@ -45,7 +321,8 @@ pub trait SymbolArtLayer {
 /// Each component (red, green, blue, alpha) is represented as an 8-bit unsigned integer,
 /// allowing for values between 0 and 255. The alpha channel controls transparency,
 /// where 0 is fully transparent and 255 is fully opaque.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Color {
     pub a: u8,
     pub r: u8,
@@ -57,10 +334,266 @@ impl Color {
     pub fn new(a: u8, r: u8, g: u8, b: u8) -> Self {
         Self { a, r, g, b }
     }
+
+    /// Parses a color from `#RRGGBB` or `#RRGGBBAA`, the `#` being optional
+    ///
+    /// The alpha defaults to 255 when only 6 digits are given.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if !(digits.len() == 6 || digits.len() == 8)
+            || !digits.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Err(SARError::InvalidHexColor(hex.to_string()));
+        }
+
+        // The digits are checked above, so every pair is a valid byte
+        let channel = |i: usize| u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap();
+        let a = if digits.len() == 8 { channel(3) } else { 255 };
+        Ok(Self::new(a, channel(0), channel(1), channel(2)))
+    }
+
+    /// Rotates the hue by `degrees`, keeping the saturation, value and alpha
+    pub fn with_hue_shift(self, degrees: f32) -> Self {
+        let (h, s, v) = self.to_hsv();
+        self.with_hsv((h + degrees).rem_euclid(360.0), s, v)
+    }
+
+    /// Multiplies the saturation by `factor`, clamped to the valid range
+    pub fn with_saturation_scale(self, factor: f32) -> Self {
+        let (h, s, v) = self.to_hsv();
+        self.with_hsv(h, (s * factor).clamp(0.0, 1.0), v)
+    }
+
+    /// Multiplies the value (brightness) by `factor`, clamped to the valid range
+    pub fn with_value_scale(self, factor: f32) -> Self {
+        let (h, s, v) = self.to_hsv();
+        self.with_hsv(h, s, (v * factor).clamp(0.0, 1.0))
+    }
+
+    /// Converts the RGB channels to hue in degrees, saturation and value in 0.0..=1.0
+    fn to_hsv(self) -> (f32, f32, f32) {
+        let [r, g, b] = [self.r, self.g, self.b].map(|c| c as f32 / 255.0);
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+
+        let h = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let s = if max == 0.0 { 0.0 } else { delta / max };
+
+        (h, s, max)
+    }
+
+    /// Replaces the RGB channels with the given HSV color, keeping the alpha
+    fn with_hsv(self, h: f32, s: f32, v: f32) -> Self {
+        let c = v * s;
+        let x = c * (1.0 - ((h / 60.0).rem_euclid(2.0) - 1.0).abs());
+        let (r, g, b) = match (h / 60.0) as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+
+        let channel = |value: f32| ((value + v - c) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Self::new(self.a, channel(r), channel(g), channel(b))
+    }
+
+    /// Formats the color as `#rrggbb`, or `#rrggbbaa` if it isn't opaque
+    pub fn to_hex(&self) -> String {
+        let hex = format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b);
+        if self.a == 255 {
+            hex
+        } else {
+            format!("{hex}{:02x}", self.a)
+        }
+    }
 }
 
+#[cfg(feature = "render")]
 impl From<Color> for image::Rgba<u8> {
     fn from(value: Color) -> Self {
         image::Rgba([value.r, value.g, value.b, value.a])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{MockLayer, MockSymbolArt};
+
+    #[test]
+    fn test_visible_layers() {
+        let mut hidden = MockLayer::new((0, 0), (10, 10), 1, Color::new(255, 0, 0, 255));
+        hidden.is_hidden = true;
+        let sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((0, 0), (10, 10), 1, Color::new(255, 255, 0, 0)),
+            hidden,
+            MockLayer::new((0, 0), (10, 10), 2, Color::new(255, 0, 255, 0)),
+        ]);
+
+        let ids = sa
+            .visible_layers()
+            .map(|layer| layer.symbol().id().id())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [1, 2]);
+        assert_eq!(sa.visible_layers().next_back().unwrap().symbol_id, 2);
+    }
+
+    #[test]
+    fn test_find_duplicate_layers() {
+        let color = Color::new(255, 0, 0, 255);
+        let layer = MockLayer::new((0, 0), (10, 10), 1, color);
+        let mut nearly_opaque = layer.clone();
+        nearly_opaque.alpha = 1.0 - 1e-6;
+        let mut hidden = layer.clone();
+        hidden.is_hidden = true;
+        let mut multiplied = layer.clone();
+        multiplied.blend_mode = BlendMode::Multiply;
+        let sa = MockSymbolArt::with_layers(vec![
+            layer.clone(),
+            MockLayer::new((0, 0), (10, 11), 1, color),
+            nearly_opaque,
+            hidden,
+            MockLayer::new((0, 0), (10, 10), 2, color),
+            multiplied,
+            layer,
+        ]);
+
+        assert_eq!(sa.find_duplicate_layers(), vec![(0, 2), (0, 6), (2, 6)]);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let color = Color::new(255, 0, 0, 255);
+        let sa = MockSymbolArt::with_layers(vec![MockLayer::new((0, 0), (10, 10), 1, color)]);
+        // The hash is fixed, so it can be stored and compared across runs
+        assert_eq!(sa.content_hash(), 0x34fb_acad_4aa8_c1ca);
+        assert_eq!(sa.content_hash(), sa.clone().content_hash());
+
+        let mut moved = sa.clone();
+        moved.layers[0].top_left.x = 1;
+        let mut hidden = sa.clone();
+        hidden.layers[0].is_hidden = true;
+        let mut resized = sa.clone();
+        resized.width = 192;
+        for other in [moved, hidden, resized] {
+            assert_ne!(sa.content_hash(), other.content_hash());
+        }
+    }
+
+    #[test]
+    fn test_corners() {
+        let layer = MockLayer::new((10, 20), (30, 40), 1, Color::new(255, 0, 0, 255));
+        let corners = layer.corners().map(|p| (p.x, p.y));
+
+        assert_eq!(corners, [(10, 20), (30, 20), (30, 40), (10, 40)]);
+    }
+
+    #[test]
+    fn test_stats() {
+        let red = Color::new(255, 255, 0, 0);
+        let blue = Color::new(255, 0, 0, 255);
+        let mut hidden = MockLayer::new((0, 0), (200, 200), 4, red);
+        hidden.is_hidden = true;
+        let sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((10, 20), (30, 40), 1, blue),
+            hidden,
+            MockLayer::new((50, 5), (60, 25), 1, red),
+            MockLayer::new((0, 0), (0, 0), 2, blue),
+        ]);
+
+        assert_eq!(
+            sa.stats(),
+            SaStats {
+                layers: 4,
+                visible_layers: 3,
+                symbols: 2,
+                colors: 2,
+                bounding_size: (60, 40),
+            }
+        );
+
+        let empty = MockSymbolArt::with_layers(vec![]);
+        assert_eq!(empty.stats(), SaStats::default());
+    }
+
+    #[test]
+    fn test_palette() {
+        let red = Color::new(255, 255, 0, 0);
+        let blue = Color::new(255, 0, 0, 255);
+        let mut hidden = MockLayer::new((0, 0), (1, 1), 1, Color::new(255, 0, 255, 0));
+        hidden.is_hidden = true;
+        let sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((0, 0), (1, 1), 1, blue),
+            hidden,
+            MockLayer::new((0, 0), (1, 1), 2, red),
+            MockLayer::new((0, 0), (1, 1), 3, blue),
+        ]);
+
+        assert_eq!(sa.palette(), vec![blue, red]);
+        assert_eq!(sa.palette_with_counts(), vec![(blue, 2), (red, 1)]);
+    }
+
+    #[test]
+    fn test_color_from_hex() {
+        let color = Color::from_hex("#ff8000").unwrap();
+        assert_eq!((color.a, color.r, color.g, color.b), (255, 255, 128, 0));
+
+        let color = Color::from_hex("FF800080").unwrap();
+        assert_eq!((color.a, color.r, color.g, color.b), (128, 255, 128, 0));
+
+        for hex in ["#ff80", "#gg8000", "#ff80000", ""] {
+            assert!(matches!(
+                Color::from_hex(hex),
+                Err(SARError::InvalidHexColor(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_color_with_hue_shift() {
+        let green = Color::new(200, 255, 0, 0).with_hue_shift(120.0);
+        assert_eq!((green.a, green.r, green.g, green.b), (200, 0, 255, 0));
+
+        let red = Color::new(255, 0, 0, 255).with_hue_shift(-600.0);
+        assert_eq!((red.r, red.g, red.b), (255, 0, 0));
+
+        let gray = Color::new(255, 128, 128, 128).with_hue_shift(90.0);
+        assert_eq!((gray.r, gray.g, gray.b), (128, 128, 128));
+    }
+
+    #[test]
+    fn test_color_with_saturation_and_value_scale() {
+        let color = Color::new(255, 255, 128, 0);
+
+        let gray = color.with_saturation_scale(0.0);
+        assert_eq!((gray.r, gray.g, gray.b), (255, 255, 255));
+
+        let saturated = Color::new(255, 255, 128, 128).with_saturation_scale(10.0);
+        assert_eq!((saturated.r, saturated.g, saturated.b), (255, 0, 0));
+
+        let dark = color.with_value_scale(0.5);
+        assert_eq!((dark.a, dark.r, dark.g, dark.b), (255, 128, 64, 0));
+
+        let black = color.with_value_scale(-1.0);
+        assert_eq!((black.r, black.g, black.b), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_color_to_hex() {
+        assert_eq!(Color::new(255, 255, 128, 0).to_hex(), "#ff8000");
+        assert_eq!(Color::new(128, 255, 128, 0).to_hex(), "#ff800080");
+
+        let color = Color::from_hex(&Color::new(16, 1, 2, 3).to_hex()).unwrap();
+        assert_eq!((color.a, color.r, color.g, color.b), (16, 1, 2, 3));
+    }
+}
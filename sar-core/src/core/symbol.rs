@@ -1,4 +1,17 @@
+/// The highest symbol id known by the image sheets
+pub(crate) const MAX_SYMBOL_ID: u32 = 768;
+
+/// Whether the image sheets hold a symbol for `id`
+///
+/// The ids run from 1 to 80 and from 240 to `MAX_SYMBOL_ID`, the ones in
+/// between and 0 aren't used by the game.
+pub(crate) fn is_known_symbol(id: u32) -> bool {
+    matches!(id, 1..=80 | 240..=MAX_SYMBOL_ID)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct SymbolId(u32);
 
 impl SymbolId {
@@ -24,6 +37,7 @@ impl From<u16> for SymbolId {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Symbol {
     id: SymbolId,
 }
@@ -0,0 +1,265 @@
+//! C bindings to parse and draw SymbolArts from other languages
+//!
+//! Every function returns a `SarErrorCode`, and the message of the last error
+//! on the calling thread is available from `sar_last_error_message`. The
+//! header is generated with `cbindgen --config cbindgen.toml --output include/sar.h`
+//! from the sar-core directory.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    sync::OnceLock,
+};
+
+use crate::{core::result::SARError, Payload, SymbolArtDrawer};
+
+/// A parsed SymbolArt, only accessed through a `SarHandle`
+pub struct SarSymbolArt {
+    payload: Payload,
+}
+
+/// An opaque handle to a parsed SymbolArt, released with `sar_free`
+pub type SarHandle = *mut SarSymbolArt;
+
+/// The status returned by the functions, zero on success
+///
+/// Panics are caught and reported as `Panic` rather than unwinding into C.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SarErrorCode {
+    Ok = 0,
+    NullPointer = 1,
+    Panic = 2,
+    InvalidFileHeader = 3,
+    Io = 4,
+    SymbolNotFound = 5,
+    Image = 6,
+    Projection = 7,
+    LayerOutOfRange = 8,
+    InvalidResource = 9,
+    InvalidCanvasSize = 10,
+    UnsupportedSize = 11,
+    TooManyLayers = 12,
+    TruncatedLayers = 13,
+    InvalidLayerSymbol = 14,
+    InvalidHexColor = 15,
+    Cancelled = 16,
+}
+
+impl From<&SARError> for SarErrorCode {
+    fn from(e: &SARError) -> Self {
+        match e {
+            SARError::InvalidFileHeader => Self::InvalidFileHeader,
+            SARError::IoError(_) => Self::Io,
+            SARError::SymbolNotFound(_) => Self::SymbolNotFound,
+            SARError::ImageError(_) => Self::Image,
+            SARError::ProjectionError(..) => Self::Projection,
+            SARError::LayerOutOfRange(_, _) => Self::LayerOutOfRange,
+            SARError::InvalidResource(_) => Self::InvalidResource,
+            SARError::InvalidCanvasSize(_, _) => Self::InvalidCanvasSize,
+            SARError::UnsupportedSize(_, _) => Self::UnsupportedSize,
+            SARError::TooManyLayers(_, _) => Self::TooManyLayers,
+            SARError::TruncatedLayers(_, _) => Self::TruncatedLayers,
+            SARError::InvalidLayerSymbol(_, _) => Self::InvalidLayerSymbol,
+            SARError::InvalidHexColor(_) => Self::InvalidHexColor,
+            SARError::Cancelled => Self::Cancelled,
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Records the error message for `sar_last_error_message` and returns its code
+fn fail(code: SarErrorCode, message: String) -> SarErrorCode {
+    // Interior NUL bytes can't be represented, so the message is cut there
+    let message = CString::new(message).unwrap_or_else(|e| {
+        let end = e.nul_position();
+        CString::new(&e.into_vec()[..end]).unwrap()
+    });
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    code
+}
+
+/// Runs `f`, turning its error or a panic into a code
+fn guard<F>(f: F) -> SarErrorCode
+where
+    F: FnOnce() -> Result<(), (SarErrorCode, String)>,
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => SarErrorCode::Ok,
+        Ok(Err((code, message))) => fail(code, message),
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            fail(SarErrorCode::Panic, message)
+        }
+    }
+}
+
+fn sar_error(e: SARError) -> (SarErrorCode, String) {
+    ((&e).into(), e.to_string())
+}
+
+fn null_pointer(name: &str) -> (SarErrorCode, String) {
+    (SarErrorCode::NullPointer, format!("`{name}` is null"))
+}
+
+/// Parses the `len` bytes at `data` and stores a new handle in `out`
+///
+/// On failure `out` is left untouched.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes and `out` to a writable handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sar_parse(
+    data: *const u8,
+    len: usize,
+    out: *mut SarHandle,
+) -> SarErrorCode {
+    guard(|| {
+        if data.is_null() {
+            return Err(null_pointer("data"));
+        }
+        if out.is_null() {
+            return Err(null_pointer("out"));
+        }
+
+        // SAFETY: the caller guarantees `data` points to `len` bytes
+        let bytes = unsafe { std::slice::from_raw_parts(data, len) };
+        let payload = crate::parse(bytes.to_vec()).map_err(sar_error)?;
+        let handle = Box::into_raw(Box::new(SarSymbolArt { payload }));
+        // SAFETY: the caller guarantees `out` is writable
+        unsafe { out.write(handle) };
+        Ok(())
+    })
+}
+
+/// Draws the SymbolArt at `scale` and stores the PNG bytes in `out` and `out_len`
+///
+/// The bytes are released with `sar_free_bytes`. On failure `out` and
+/// `out_len` are left untouched.
+///
+/// # Safety
+///
+/// `handle` must come from `sar_parse` and not be freed yet, and `out` and
+/// `out_len` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sar_draw(
+    handle: SarHandle,
+    scale: f32,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+) -> SarErrorCode {
+    static DRAWER: OnceLock<SymbolArtDrawer> = OnceLock::new();
+
+    guard(|| {
+        if handle.is_null() {
+            return Err(null_pointer("handle"));
+        }
+        if out.is_null() || out_len.is_null() {
+            return Err(null_pointer("out"));
+        }
+
+        // SAFETY: the caller guarantees `handle` is a live handle from `sar_parse`
+        let sa = unsafe { &*handle };
+        let drawer = DRAWER.get_or_init(SymbolArtDrawer::new);
+        let png = drawer.draw_png(&sa.payload, scale).map_err(sar_error)?;
+
+        let png = Box::into_raw(png.into_boxed_slice());
+        // SAFETY: the caller guarantees `out` and `out_len` are writable
+        unsafe {
+            out_len.write(png.len());
+            out.write(png.cast());
+        }
+        Ok(())
+    })
+}
+
+/// Releases a handle returned by `sar_parse`, doing nothing if it is null
+///
+/// # Safety
+///
+/// `handle` must come from `sar_parse` and not be freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sar_free(handle: SarHandle) {
+    if !handle.is_null() {
+        // SAFETY: the caller guarantees `handle` is a live handle from `sar_parse`
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+/// Releases the bytes returned by `sar_draw`, doing nothing if they are null
+///
+/// # Safety
+///
+/// `bytes` and `len` must be the values stored by `sar_draw`, and the bytes must
+/// not be freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sar_free_bytes(bytes: *mut u8, len: usize) {
+    if !bytes.is_null() {
+        // SAFETY: the caller guarantees the bytes were allocated by `sar_draw` with `len`
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(bytes, len)) });
+    }
+}
+
+/// The message of the last error on the calling thread, or null if none occurred
+///
+/// The string is owned by the library and stays valid until the next error on
+/// the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn sar_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use super::*;
+    use crate::test::RAW_FILE;
+
+    #[test]
+    fn test_parse_and_draw() {
+        let mut handle = ptr::null_mut();
+        let code = unsafe { sar_parse(RAW_FILE.as_ptr(), RAW_FILE.len(), &mut handle) };
+        assert_eq!(code, SarErrorCode::Ok);
+        assert!(!handle.is_null());
+
+        let (mut png, mut len) = (ptr::null_mut(), 0);
+        let code = unsafe { sar_draw(handle, 1.0, &mut png, &mut len) };
+        assert_eq!(code, SarErrorCode::Ok);
+
+        let image = image::load_from_memory(unsafe { std::slice::from_raw_parts(png, len) });
+        assert_eq!(image.unwrap().width(), 193);
+        unsafe {
+            sar_free_bytes(png, len);
+            sar_free(handle);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut handle = ptr::null_mut();
+        let bytes = b"nope";
+        let code = unsafe { sar_parse(bytes.as_ptr(), bytes.len(), &mut handle) };
+        assert_eq!(code, SarErrorCode::InvalidFileHeader);
+        assert!(handle.is_null());
+        let message = unsafe { CStr::from_ptr(sar_last_error_message()) };
+        assert_eq!(message.to_str().unwrap(), "invalid file format");
+
+        let code = unsafe { sar_parse(ptr::null(), 0, &mut handle) };
+        assert_eq!(code, SarErrorCode::NullPointer);
+    }
+}
@@ -8,6 +8,7 @@
 //! - Parse SAR files into a structured format
 //! - Render SymbolArt compositions into images
 //! - Manipulate and inspect SymbolArt properties
+//! - Build SymbolArts in code with [`SymbolArtBuilder`] and write them back to SAR files
 //!
 //! ## Basic Usage
 //!
@@ -76,6 +77,9 @@
 //! // Get basic information
 //! println!("Name: {}", symbol_art.name());
 //! println!("Author ID: {}", symbol_art.author_id());
+//! if let Some(sound) = symbol_art.sound_effect() {
+//!     println!("Sound effect: {}", sound);
+//! }
 //! println!("Dimensions: {}x{}", symbol_art.width(), symbol_art.height());
 //!
 //! // Inspect layers
@@ -98,7 +102,7 @@
 //!   - Sound effect (1 byte)
 //! - Layer data (variable length)
 //!   - Position data (8 bytes per layer)
-//!   - Layer properties (4 bytes per layer)
+//!   - Layer properties (8 bytes per layer)
 //! - Name data (UTF-16LE, up to 13 characters)
 //!
 //! ## Error Handling
@@ -132,6 +136,33 @@
 //! - `rayon`: For parallel processing
 //! - `blowfish`: For file decryption
 //! - `ages_prs`: For file decompression
+//! - `base64`: For embedding symbols in SVG exports
+//! - `png`: For writing the physical resolution of PNG outputs
+//! - `color_quant`: For reducing the colors of the output
+//!
+//! Only `blowfish` and `ages_prs` are needed to parse, the others come with the
+//! `render` feature.
+//!
+//! ## Feature Flags
+//!
+//! - `render`: Enabled by default. Adds the `renderer` module, [`SymbolArtDrawer`] and
+//!   `draw`, with the `image`, `imageproc`, `rayon`, `png`, `color_quant` and `base64`
+//!   dependencies. Without it only the parser, the builder and `write` are built, which
+//!   still need `std`. The features below that draw SymbolArts enable it
+//! - `serde`: Implements `Serialize` and `Deserialize` for [`Payload`] and the types it is made of
+//! - `tokio`: Adds `parse_file_async` to read SAR files on a tokio runtime, and `render_stream`
+//!   to render a stream of them with bounded concurrency
+//! - `gpu`: Adds `SymbolArtDrawer::with_gpu` to composite the layers with wgpu, falling back to the CPU
+//!   renderer when no adapter is available
+//! - `wasm`: Exports `parse` and `draw` to JavaScript with `wasm-bindgen`, drawing to PNG bytes
+//! - `pyo3`: Builds the `sar` Python module, with `parse`, `SymbolArt.layers` and `draw`
+//! - `ffi`: Exports C functions to parse and draw SymbolArts, declared in `include/sar.h`
+//! - `tracing`: Records every render as `tracing` spans, timing the projection, warp and
+//!   composite phases
+//! - `webp`: Adds `SymbolArtDrawer::draw_webp` to encode renders as lossless or lossy WebP
+//!   with libwebp
+//! - `mmap`: Adds `parse_mmap` to parse SAR files from a memory map instead of a buffer
+//! - `test-util`: Adds the `testing` module, to compare renders by their pixels in tests
 //!
 //! ## License
 //!
@@ -139,11 +170,31 @@
 
 mod core;
 mod parser;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "render")]
 pub mod renderer;
+#[cfg(feature = "pyo3")]
+mod python;
+#[cfg(all(feature = "render", any(test, feature = "test-util")))]
+pub mod testing;
+#[cfg(feature = "wasm")]
+mod wasm;
 pub use core::result::Result;
-pub use core::sa::{Color, SymbolArt, SymbolArtLayer};
-pub use parser::payload::parse;
+pub use core::sa::{BlendMode, Color, SaStats, SoundId, SymbolArt, SymbolArtLayer};
+pub use core::symbol::{Symbol, SymbolId};
+pub use parser::payload::{parse, parse_any, parse_file, parse_reader, parse_strict, write};
+#[cfg(feature = "tokio")]
+pub use parser::payload::parse_file_async;
+#[cfg(feature = "mmap")]
+pub use parser::payload::parse_mmap;
+pub use parser::builder::SymbolArtBuilder;
+pub use parser::payload::{Header, Layer, Payload};
+#[cfg(feature = "render")]
 pub use renderer::SymbolArtDrawer;
+#[cfg(feature = "tokio")]
+pub use renderer::render_stream;
+#[cfg(feature = "render")]
 pub use renderer::default::draw;
 
 #[cfg(test)]
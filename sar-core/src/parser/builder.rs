@@ -0,0 +1,273 @@
+use crate::core::{
+    result::{Result, SARError},
+    sa::{Color, Position, SoundId, MAX_LAYERS},
+    symbol::{is_known_symbol, SymbolId},
+};
+
+use super::payload::{
+    Header, Layer, Payload, ALPHA_FACTOR, COLOR_FACTOR, HEADER_SIZE_NORMAL, HEADER_SIZE_TEAM_FLAG,
+};
+
+/// Builds a SymbolArt in code instead of parsing it from a SAR file
+///
+/// Nothing is checked until `build`, which returns the same `Payload` as
+/// `parse` would for a file holding the art, so it can be drawn or passed to
+/// `write` right away.
+///
+/// ```
+/// use sar_core::{write, Color, SymbolArt, SymbolArtBuilder, SymbolId};
+///
+/// let sa = SymbolArtBuilder::new()
+///     .size(193, 96)
+///     .name("generated")
+///     .add_layer(
+///         SymbolId::new(17),
+///         Color::new(255, 255, 0, 0),
+///         [(100, 100), (150, 100), (150, 150), (100, 150)],
+///     )
+///     .build()
+///     .unwrap();
+/// assert_eq!(sa.layers().len(), 1);
+/// assert_eq!(sar_core::parse(write(&sa).unwrap()).unwrap(), sa);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SymbolArtBuilder {
+    size: (u8, u8),
+    name: String,
+    author_id: u32,
+    sound_effect: Option<SoundId>,
+    layers: Vec<PendingLayer>,
+}
+
+/// A layer as it was added, validated by `SymbolArtBuilder::build`
+#[derive(Debug, Clone)]
+struct PendingLayer {
+    symbol_id: SymbolId,
+    color: Color,
+    corners: [(u8, u8); 4],
+    hidden: bool,
+}
+
+impl SymbolArtBuilder {
+    /// Starts an empty 193x96 SymbolArt without a name
+    pub fn new() -> Self {
+        Self {
+            size: (193, 96),
+            name: String::new(),
+            author_id: 0,
+            sound_effect: None,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Sets the size of the SymbolArt, either 193x96 or 32x32 like the game allows
+    pub fn size(mut self, width: u8, height: u8) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    /// Sets the name, which is cut to its first 13 UTF-16 code units like `write` does
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Sets the id of the author, 0 unless set
+    pub fn author_id(mut self, author_id: u32) -> Self {
+        self.author_id = author_id;
+        self
+    }
+
+    /// Sets the sound effect played with the SymbolArt, `None` for a silent one
+    pub fn sound_effect(mut self, sound: Option<SoundId>) -> Self {
+        self.sound_effect = sound;
+        self
+    }
+
+    /// Adds a layer below the ones added before
+    ///
+    /// The corners are given in the order of `SymbolArtLayer::corners`: top-left,
+    /// top-right, bottom-right and bottom-left. They are single bytes like in
+    /// SAR files, so they always fall within the 256x256 canvas. The color is
+    /// quantized to the 6-bit channels and 3-bit alpha of the format.
+    pub fn add_layer(self, symbol_id: SymbolId, color: Color, corners: [(u8, u8); 4]) -> Self {
+        self.add_layer_with_visibility(symbol_id, color, corners, false)
+    }
+
+    /// Adds a layer like `add_layer`, which the renderer skips unless told otherwise
+    pub fn add_hidden_layer(
+        self,
+        symbol_id: SymbolId,
+        color: Color,
+        corners: [(u8, u8); 4],
+    ) -> Self {
+        self.add_layer_with_visibility(symbol_id, color, corners, true)
+    }
+
+    fn add_layer_with_visibility(
+        mut self,
+        symbol_id: SymbolId,
+        color: Color,
+        corners: [(u8, u8); 4],
+        hidden: bool,
+    ) -> Self {
+        self.layers.push(PendingLayer {
+            symbol_id,
+            color,
+            corners,
+            hidden,
+        });
+        self
+    }
+
+    /// Validates the SymbolArt and builds it
+    ///
+    /// Fails with `SARError::UnsupportedSize` for a size other than 193x96 or
+    /// 32x32, with `SARError::TooManyLayers` for more than 225 layers, and with
+    /// `SARError::InvalidLayerSymbol` for a symbol id the sheets don't have,
+    /// like 0 or the ids from 81 to 239, as `parse_strict` does.
+    pub fn build(self) -> Result<Payload> {
+        let (height, width) = match self.size {
+            (193, 96) => (HEADER_SIZE_NORMAL, 193),
+            (32, 32) => (HEADER_SIZE_TEAM_FLAG, HEADER_SIZE_TEAM_FLAG),
+            (width, height) => return Err(SARError::UnsupportedSize(width, height)),
+        };
+        if self.layers.len() > MAX_LAYERS {
+            return Err(SARError::TooManyLayers(self.layers.len(), MAX_LAYERS));
+        }
+
+        let layers = self
+            .layers
+            .into_iter()
+            .enumerate()
+            .map(|(index, layer)| {
+                let PendingLayer {
+                    symbol_id,
+                    color,
+                    corners,
+                    hidden,
+                } = layer;
+                if !is_known_symbol(symbol_id.id()) {
+                    return Err(SARError::InvalidLayerSymbol(index, symbol_id));
+                }
+
+                let [top_left, top_right, bottom_right, bottom_left] =
+                    corners.map(|(x, y)| Position { x, y });
+                Ok(Layer {
+                    top_left,
+                    bottom_left,
+                    top_right,
+                    bottom_right,
+                    is_hidden: hidden,
+                    symbol_id: symbol_id.id() as u16,
+                    alpha: ((color.a as u32 + ALPHA_FACTOR as u32 / 2) / ALPHA_FACTOR as u32) as u8,
+                    color_r: color.r / COLOR_FACTOR,
+                    color_g: color.g / COLOR_FACTOR,
+                    color_b: color.b / COLOR_FACTOR,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let units = self.name.encode_utf16().take(13).collect::<Vec<_>>();
+        Ok(Payload {
+            header: Header {
+                author_id: self.author_id,
+                layers: layers.len() as u8,
+                height,
+                width,
+                sound_effect: self.sound_effect.map_or(0, |sound| sound.id()),
+            },
+            layers,
+            name: String::from_utf16_lossy(&units),
+        })
+    }
+}
+
+impl Default for SymbolArtBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::symbol::MAX_SYMBOL_ID, parse, write, SymbolArt, SymbolArtLayer};
+
+    #[test]
+    fn test_build() {
+        let red = Color::new(255, 255, 0, 0);
+        let sa = SymbolArtBuilder::new()
+            .name("a generated SymbolArt")
+            .author_id(42)
+            .sound_effect(Some(SoundId::new(3)))
+            .add_layer(
+                SymbolId::new(17),
+                red,
+                [(100, 100), (150, 100), (150, 150), (100, 150)],
+            )
+            .add_hidden_layer(SymbolId::new(18), red, [(0, 0), (10, 0), (10, 10), (0, 10)])
+            .build()
+            .unwrap();
+
+        assert_eq!((sa.width(), sa.height()), (193, 96));
+        assert_eq!(sa.name(), "a generated S");
+        assert_eq!(sa.author_id(), 42);
+        assert_eq!(sa.sound_effect(), Some(SoundId::new(3)));
+        let layers = sa.layers();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].top_right(), Position { x: 150, y: 100 });
+        assert_eq!(layers[0].bottom_left(), Position { x: 100, y: 150 });
+        assert_eq!(layers[0].color(), Color::new(255, 252, 0, 0));
+        assert!(!layers[0].is_hidden() && layers[1].is_hidden());
+        assert_eq!(parse(write(&sa).unwrap()).unwrap(), sa);
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn test_build_draw() {
+        use crate::{renderer::draw::Drawer, SymbolArtDrawer};
+
+        let sa = SymbolArtBuilder::new()
+            .add_layer(
+                SymbolId::new(17),
+                Color::new(255, 255, 0, 0),
+                [(100, 100), (150, 100), (150, 150), (100, 150)],
+            )
+            .build()
+            .unwrap();
+        let image = SymbolArtDrawer::default().draw(&sa).unwrap();
+        assert_eq!(image.dimensions(), (193, 96));
+        assert_eq!(image.get_pixel(125 - 32, 125 - 80).0, [252, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_build_errors() {
+        let corners = [(0, 0), (1, 0), (1, 1), (0, 1)];
+        let color = Color::new(255, 0, 0, 0);
+
+        let team = SymbolArtBuilder::new().size(32, 32).build().unwrap();
+        assert_eq!((team.width(), team.height()), (32, 32));
+        assert!(matches!(
+            SymbolArtBuilder::new().size(64, 64).build(),
+            Err(SARError::UnsupportedSize(64, 64))
+        ));
+        for unknown in [0, 100, 239, MAX_SYMBOL_ID + 1] {
+            assert!(matches!(
+                SymbolArtBuilder::new()
+                    .add_layer(SymbolId::new(17), color, corners)
+                    .add_layer(SymbolId::new(unknown), color, corners)
+                    .build(),
+                Err(SARError::InvalidLayerSymbol(1, id)) if id.id() == unknown
+            ));
+        }
+
+        let full = (0..=MAX_LAYERS).fold(SymbolArtBuilder::new(), |builder, _| {
+            builder.add_layer(SymbolId::new(17), color, corners)
+        });
+        assert!(matches!(
+            full.build(),
+            Err(SARError::TooManyLayers(226, 225))
+        ));
+    }
+}
@@ -3,7 +3,7 @@ use std::io::{Cursor, Read};
 use crate::core::result::{Result, SARError};
 use ages_prs::ModernPrsDecoder;
 use blowfish::{
-    cipher::{generic_array::GenericArray, BlockDecrypt, KeyInit},
+    cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyInit},
     BlowfishLE,
 };
 
@@ -19,15 +19,29 @@ pub fn decrypt(bytes: &mut [u8]) {
     }
 }
 
+pub fn encrypt(bytes: &mut [u8]) {
+    // It's safe to unwrap because the key is hardcoded and known
+    let cipher = BlowfishLE::new_from_slice(KEY).unwrap();
+    // encrypt the same blocks that are decrypted on parse
+    for block in bytes.chunks_exact_mut(8) {
+        let block = GenericArray::from_mut_slice(block);
+        cipher.encrypt_block(block);
+    }
+}
+
 pub fn decompress(bytes: &mut [u8]) -> Result<Box<[u8]>> {
     // XOR every byte in the buffer with 0x95
     bytes.iter_mut().for_each(|b| *b ^= 0x95);
-    // decompress the PRS
-    let mut decoder = ModernPrsDecoder::new(Cursor::new(&bytes[..]));
+    Ok(Box::from(decompress_prs(bytes)?))
+}
+
+/// Decompresses a PRS stream in the variant PSO2 uses
+pub fn decompress_prs(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = ModernPrsDecoder::new(Cursor::new(bytes));
     let mut result = Vec::new();
     decoder.read_to_end(&mut result)?;
 
-    Ok(Box::from(result))
+    Ok(result)
 }
 
 #[derive(Debug, PartialEq)]
@@ -37,7 +51,7 @@ pub enum Compression {
 }
 
 pub fn validate_format(bytes: &[u8]) -> Result<Compression> {
-    if bytes[0..3] != [b's', b'a', b'r'] {
+    if bytes.len() < 4 || bytes[0..3] != [b's', b'a', b'r'] {
         return Err(SARError::InvalidFileHeader);
     }
     match bytes[3] {
@@ -57,5 +71,19 @@ mod tests {
     fn test_validate_format() {
         let compression = validate_format(RAW_FILE).unwrap();
         assert_eq!(compression, Compression::Compressed);
+        assert!(validate_format(b"sa").is_err());
+    }
+
+    #[test]
+    fn test_encrypt() {
+        let original = (0..20).collect::<Vec<u8>>();
+        let mut bytes = original.clone();
+
+        encrypt(&mut bytes);
+        assert_ne!(bytes[..16], original[..16]);
+        assert_eq!(bytes[16..], original[16..]);
+
+        decrypt(&mut bytes);
+        assert_eq!(bytes, original);
     }
 }
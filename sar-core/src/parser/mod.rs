@@ -1,2 +1,3 @@
+pub(crate) mod builder;
 mod decode;
 pub(crate) mod payload;
@@ -1,23 +1,201 @@
 use crate::{
     core::{
-        result::Result,
-        sa::{self, Position, SymbolArt, SymbolArtLayer},
-        symbol,
+        result::{Result, SARError},
+        sa::{self, Position, SoundId, SymbolArt, SymbolArtLayer, MAX_LAYERS},
+        symbol::{self, is_known_symbol},
     },
     parser::decode,
 };
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
 
 /// Parses a byte array into a Payload structure
-pub fn parse(bytes: impl Into<Box<[u8]>>) -> Result<impl SymbolArt + std::fmt::Debug> {
+pub fn parse(bytes: impl Into<Box<[u8]>>) -> Result<Payload> {
     let body = get_body(bytes.into())?;
     Payload::parse(&body)
 }
 
+/// Parses a byte array into a Payload structure, rejecting malformed files
+///
+/// Unlike `parse`, which renders whatever it can make of a file, this checks
+/// that the header declares a known size and at most 225 layers, that the file
+/// holds all of the declared layers, and that every layer uses a symbol id the
+/// image sheets hold, from 1 to 80 or from 240 to 768. Corner coordinates are single bytes, so they always fall within the
+/// 256x256 canvas and aren't checked.
+pub fn parse_strict(bytes: impl Into<Box<[u8]>>) -> Result<Payload> {
+    let body = get_body(bytes.into())?;
+    let header_size = std::mem::size_of::<Header>();
+    if body.len() < header_size {
+        return Err(SARError::InvalidFileHeader);
+    }
+
+    let header = Header::parse(&body[..header_size])?;
+    if !header.has_known_size() {
+        return Err(SARError::InvalidFileHeader);
+    }
+
+    let declared = header.layers() as usize;
+    if declared > MAX_LAYERS {
+        return Err(SARError::TooManyLayers(declared, MAX_LAYERS));
+    }
+    let available = (body.len() - header_size) / std::mem::size_of::<Layer>();
+    if available < declared {
+        return Err(SARError::TruncatedLayers(declared, available));
+    }
+
+    let payload = Payload::parse(&body)?;
+    for (index, layer) in payload.layers.iter().enumerate() {
+        if !is_known_symbol(layer.symbol_id as u32) {
+            return Err(SARError::InvalidLayerSymbol(index, layer.symbol().id()));
+        }
+    }
+
+    Ok(payload)
+}
+
+/// Parses a SAR file that may be wrapped in another format
+///
+/// The following variants are recognized, in this order:
+/// - A SAR file, compressed or not, as read by `parse` if it declares a known size
+/// - A SAR file after a wrapper header of up to 4096 bytes, found by the
+///   `sar` magic and compression flag of its own header. As the header may
+///   contain them by chance, the file must also pass `parse_strict`
+/// - Either of the above compressed as a whole with PRS, in the variant PSO2 uses
+///
+/// Anything else, including game archives holding more than one file, fails
+/// with `SARError::InvalidFileHeader`.
+pub fn parse_any(bytes: impl Into<Box<[u8]>>) -> Result<Payload> {
+    let bytes = bytes.into();
+    if let Some(payload) = parse_unwrapped(&bytes) {
+        return Ok(payload);
+    }
+
+    decode::decompress_prs(&bytes)
+        .ok()
+        .and_then(|unpacked| parse_unwrapped(&unpacked))
+        .ok_or(SARError::InvalidFileHeader)
+}
+
+/// How far into a wrapped file `parse_any` looks for the SAR header
+const MAX_WRAPPER_HEADER: usize = 4096;
+
+/// Parses the first SAR file found within `MAX_WRAPPER_HEADER` bytes of the start
+///
+/// A file at the start is parsed leniently, but must still declare a known
+/// size, as the width and height of a `Payload` are only defined for those.
+fn parse_unwrapped(bytes: &[u8]) -> Option<Payload> {
+    let end = bytes.len().min(MAX_WRAPPER_HEADER + 4);
+    bytes[..end]
+        .windows(4)
+        .enumerate()
+        .filter(|(_, magic)| decode::validate_format(magic).is_ok())
+        .find_map(|(start, _)| match start {
+            0 => parse(bytes)
+                .ok()
+                .filter(|payload| payload.header.has_known_size()),
+            _ => parse_strict(&bytes[start..]).ok(),
+        })
+}
+
+/// Parses a SAR file from a reader into a Payload structure
+///
+/// The file header is read and validated first, so a reader that isn't a SAR
+/// file is rejected without consuming the rest. The body is then buffered,
+/// since it's encrypted and compressed as a whole.
+pub fn parse_reader<R: Read>(mut reader: R) -> Result<Payload> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    let compression = decode::validate_format(&magic)?;
+
+    let mut body = Vec::new();
+    reader.read_to_end(&mut body)?;
+    Payload::parse(&decode_body(compression, &mut body)?)
+}
+
+/// Reads and parses the SAR file at `path`
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Payload> {
+    parse_reader(BufReader::new(File::open(path)?))
+}
+
+/// Maps the SAR file at `path` into memory and parses it from there
+///
+/// Unlike `parse_file`, the file isn't copied into a buffer first. The map is
+/// private, so decrypting the body in place only copies the pages it touches
+/// and never writes to the file, and it is unmapped before returning.
+///
+/// # Safety
+///
+/// The file must not be modified or truncated by this or another process while
+/// it is parsed. The bytes of a modified file may change as they are read, and
+/// reading past the end of a truncated one raises `SIGBUS` on Unix.
+#[cfg(feature = "mmap")]
+pub unsafe fn parse_mmap(path: impl AsRef<Path>) -> Result<Payload> {
+    let file = File::open(path)?;
+    // SAFETY: the caller guarantees the file isn't changed while it is mapped
+    let mut map = unsafe { memmap2::MmapOptions::new().map_copy(&file)? };
+    let compression = decode::validate_format(&map)?;
+    let body = &mut map[4..];
+    decode::decrypt(body);
+    match compression {
+        decode::Compression::None => Payload::parse(body),
+        decode::Compression::Compressed => Payload::parse(&decode::decompress(body)?),
+    }
+}
+
+/// Reads the SAR file at `path` without blocking the async runtime, then parses it
+#[cfg(feature = "tokio")]
+pub async fn parse_file_async(path: impl AsRef<Path>) -> Result<Payload> {
+    parse(tokio::fs::read(path).await?)
+}
+
+/// Serializes a SymbolArt into the binary SAR format
+///
+/// The file is always written uncompressed, so it isn't byte-identical to a
+/// compressed source, but parsing it yields the same SymbolArt. Some fields
+/// can't be reconstructed losslessly:
+/// - Colors are quantized back to the 6-bit channels and 3-bit alpha of the format
+/// - The name is truncated to 13 UTF-16 code units
+/// - The secondary color data of each layer isn't parsed and is written as zeros
+/// - The layer alpha of `SymbolArtLayer::alpha` has no place in the file
+pub fn write<S: SymbolArt>(sa: &S) -> Result<Vec<u8>> {
+    let layers = sa.layers();
+    let layer_count =
+        u8::try_from(layers.len()).map_err(|_| SARError::TooManyLayers(layers.len(), 255))?;
+    let (height, width) = match (sa.width(), sa.height()) {
+        (193, 96) => (HEADER_SIZE_NORMAL, 193),
+        (32, 32) => (HEADER_SIZE_TEAM_FLAG, HEADER_SIZE_TEAM_FLAG),
+        (width, height) => return Err(SARError::UnsupportedSize(width, height)),
+    };
+
+    let mut body = Vec::new();
+    body.extend_from_slice(&sa.author_id().to_be_bytes());
+    let sound_effect = sa.sound_effect().map_or(0, |sound| sound.id());
+    body.extend_from_slice(&[layer_count, height, width, sound_effect]);
+    for layer in &layers {
+        body.extend_from_slice(&Layer::encode(layer)?);
+    }
+    for unit in sa.name().encode_utf16().take(13) {
+        body.extend_from_slice(&unit.to_le_bytes());
+    }
+
+    decode::encrypt(&mut body);
+    let mut bytes = vec![b's', b'a', b'r', 0x04];
+    bytes.append(&mut body);
+    Ok(bytes)
+}
+
 /// Extracts and decompresses the body of the SAR file
 fn get_body(mut bytes: Box<[u8]>) -> Result<Box<[u8]>> {
     let compression = decode::validate_format(&bytes)?;
     let (_, body) = bytes.split_at_mut(4);
+    decode_body(compression, body)
+}
 
+/// Decrypts and decompresses the body following the file header
+fn decode_body(compression: decode::Compression, body: &mut [u8]) -> Result<Box<[u8]>> {
     decode::decrypt(body);
     match compression {
         decode::Compression::None => Ok(Box::from(body)),
@@ -26,21 +204,27 @@ fn get_body(mut bytes: Box<[u8]>) -> Result<Box<[u8]>> {
 }
 
 /// Represents the main payload of a SAR file containing header, layers, and name information.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Payload {
     /// The header containing metadata about the SAR file
-    header: Header,
+    pub(super) header: Header,
     /// Vector of layers that make up the SAR file content
-    layers: Vec<Layer>,
-    /// Name of the SAR file in UTF-16LE format (up to 13 characters)
-    name: Vec<u16>,
+    pub(super) layers: Vec<Layer>,
+    /// Name of the SAR file, decoded from UTF-16LE (up to 13 characters)
+    pub(super) name: String,
 }
 
 impl Payload {
     /// Parses a byte slice into a Payload structure
     pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < std::mem::size_of::<Header>() {
+            return Err(SARError::InvalidFileHeader);
+        }
+
         let header = Header::parse(&bytes[0..std::mem::size_of::<Header>()])?;
-        let layers = Layers::parse(&bytes[std::mem::size_of::<Header>()..])?.into();
+        let layers =
+            Layers::parse(&bytes[std::mem::size_of::<Header>()..], header.layers())?.into();
         let name = Self::parse_name(bytes, &header)?;
 
         Ok(Self {
@@ -51,7 +235,10 @@ impl Payload {
     }
 
     /// Parses the name field from the byte slice
-    fn parse_name(bytes: &[u8], header: &Header) -> Result<Vec<u16>> {
+    ///
+    /// The name ends at the first NUL character, and is empty if the file ends
+    /// before it.
+    fn parse_name(bytes: &[u8], header: &Header) -> Result<String> {
         let size_of_header = std::mem::size_of::<Header>();
         let size_of_layer = std::mem::size_of::<Layer>();
         let start = size_of_header + size_of_layer * header.layers() as usize;
@@ -60,14 +247,15 @@ impl Payload {
             .chunks_exact(2)
             .take(13) // Name is at most 13 chars
             .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+            .take_while(|&unit| unit != 0)
             .collect::<Vec<_>>();
 
-        Ok(name_bytes)
+        Ok(String::from_utf16_lossy(&name_bytes))
     }
 }
 
-const HEADER_SIZE_TEAM_FLAG: u8 = 0x40;
-const HEADER_SIZE_NORMAL: u8 = 0x80;
+pub(super) const HEADER_SIZE_TEAM_FLAG: u8 = 0x40;
+pub(super) const HEADER_SIZE_NORMAL: u8 = 0x80;
 
 impl SymbolArt for Payload {
     type Layer = Layer;
@@ -96,13 +284,21 @@ impl SymbolArt for Payload {
         self.layers.clone()
     }
 
-    fn name(&self) -> String {
-        String::from_utf16_lossy(&self.name)
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn sound_effect(&self) -> Option<SoundId> {
+        match self.header.sound_effect {
+            0 => None,
+            id => Some(SoundId::new(id)),
+        }
     }
 }
 
 /// Represents the header of a SAR file containing metadata
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
     /// Author ID in big endian format
     pub(super) author_id: u32,
@@ -131,6 +327,11 @@ impl Header {
     pub(super) fn layers(&self) -> u8 {
         self.layers
     }
+
+    /// Whether the header declares one of the sizes the game supports
+    pub(super) fn has_known_size(&self) -> bool {
+        [HEADER_SIZE_NORMAL, HEADER_SIZE_TEAM_FLAG].contains(&self.height)
+    }
 }
 
 /// Represents a collection of layers in a SAR file
@@ -139,10 +340,13 @@ pub struct Layers {
 }
 
 impl Layers {
-    /// Parses a byte slice into a Layers structure
-    pub(super) fn parse(bytes: &[u8]) -> Result<Self> {
+    /// Parses up to `count` layers from a byte slice into a Layers structure
+    ///
+    /// The name follows the layers, so reading past `count` would decode it as layers.
+    pub(super) fn parse(bytes: &[u8], count: u8) -> Result<Self> {
         let layers = bytes
             .chunks_exact(std::mem::size_of::<Layer>())
+            .take(count as usize)
             .map(Layer::parse)
             .collect::<Result<Vec<_>>>()?;
 
@@ -157,7 +361,8 @@ impl From<Layers> for Vec<Layer> {
 }
 
 /// Represents a single layer in a SAR file
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Layer {
     /// Top-left position of the layer
     pub(super) top_left: Position,
@@ -213,6 +418,40 @@ impl Layer {
         })
     }
 
+    /// Encodes any layer into the 16 bytes of a SAR layer
+    ///
+    /// The last 4 bytes hold secondary color data that isn't parsed, and are
+    /// written as zeros.
+    fn encode<L: SymbolArtLayer>(layer: &L) -> Result<[u8; 16]> {
+        let id = layer.symbol().id();
+        if id.id() > MASK_SYMBOL_ID >> 21 {
+            return Err(SARError::SymbolNotFound(id));
+        }
+
+        let color = layer.color();
+        let layer_data = (layer.is_hidden() as u32) << 31
+            | id.id() << 21
+            | ((color.a as u32 + ALPHA_FACTOR as u32 / 2) / ALPHA_FACTOR as u32) << 18
+            | ((color.b / COLOR_FACTOR) as u32) << 12
+            | ((color.g / COLOR_FACTOR) as u32) << 6
+            | (color.r / COLOR_FACTOR) as u32;
+
+        let mut bytes = [0; 16];
+        let positions = [
+            layer.top_left(),
+            layer.bottom_left(),
+            layer.top_right(),
+            layer.bottom_right(),
+        ];
+        for (i, position) in positions.into_iter().enumerate() {
+            bytes[i * 2] = position.x;
+            bytes[i * 2 + 1] = position.y;
+        }
+        bytes[8..12].copy_from_slice(&layer_data.to_le_bytes());
+
+        Ok(bytes)
+    }
+
     /// Extracts the hidden flag from the layer data
     fn extract_is_hidden(layer_data: u32) -> bool {
         (layer_data & LAYER_IS_HIDDEN) != 0
@@ -249,14 +488,14 @@ impl Layer {
 ///
 /// We use 37 to avoid floating point arithmetic for better performance
 /// 255 / 7 = 36.4285714286
-const ALPHA_FACTOR: u8 = 37;
+pub(super) const ALPHA_FACTOR: u8 = 37;
 
 /// The factor used to convert the color value to a 8-bit value.
 /// SAR files use a 4-bit color value, so we need to scale it up to 8-bit
 ///
 /// We use 4 to avoid floating point arithmetic for better performance
 /// 255 / 63 = 4.0476190476
-const COLOR_FACTOR: u8 = 4;
+pub(super) const COLOR_FACTOR: u8 = 4;
 
 impl SymbolArtLayer for Layer {
     fn top_left(&self) -> Position {
@@ -307,7 +546,7 @@ mod tests {
     use super::*;
     use crate::{
         core::sa::Position,
-        test::{RAW_FILE, RAW_FILE_UNCOMPRESSED},
+        test::{MockLayer, MockSymbolArt, RAW_FILE, RAW_FILE_UNCOMPRESSED},
     };
 
     #[test]
@@ -323,7 +562,6 @@ mod tests {
         let body = get_body(bytes).unwrap();
         let payload = Payload::parse(&body).unwrap();
 
-        let expected_name = &[12394, 12363, 12383, 12373, 12435]; // "なかたさん"
         let expected = Payload {
             header: Header {
                 author_id: 881302016,
@@ -347,12 +585,13 @@ mod tests {
                 };
                 104
             ],
-            name: expected_name.to_vec(),
+            name: "なかたさん".to_string(),
         };
 
         assert_eq!(payload.header, expected.header);
         assert_eq!(payload.layers.len(), expected.layers.len());
         assert_eq!(payload.name, expected.name);
+        assert_eq!(payload.sound_effect(), Some(SoundId::new(3)));
     }
 
     #[test]
@@ -361,9 +600,192 @@ mod tests {
         let body = get_body(bytes).unwrap();
         let payload = Payload::parse(&body).unwrap();
 
+        assert_eq!(payload.name(), "Thank you !!");
+    }
+
+    #[test]
+    fn test_parse_reader() {
+        let payload = parse_reader(RAW_FILE).unwrap();
+        assert_eq!(payload, parse(RAW_FILE).unwrap());
+
+        let payload = parse_reader(RAW_FILE_UNCOMPRESSED).unwrap();
+        assert_eq!(payload, parse(RAW_FILE_UNCOMPRESSED).unwrap());
+
+        assert!(matches!(
+            parse_reader(&b"png\x84"[..]),
+            Err(SARError::InvalidFileHeader)
+        ));
+        assert!(matches!(
+            parse_reader(&b"sa"[..]),
+            Err(SARError::IoError(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_file() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../fixture/sa0a1d081b8a108bb8c9847c4cd83db662.sar");
+        let expected = parse(std::fs::read(&path).unwrap()).unwrap();
+
+        assert_eq!(parse_file(&path).unwrap(), expected);
+        assert!(matches!(
+            parse_file(path.with_extension("missing")),
+            Err(SARError::IoError(_))
+        ));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_parse_mmap() {
+        let dir = std::env::temp_dir().join(format!("sar-core-mmap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (compressed, uncompressed, empty) = (
+            dir.join("compressed.sar"),
+            dir.join("uncompressed.sar"),
+            dir.join("empty.sar"),
+        );
+        std::fs::write(&compressed, RAW_FILE).unwrap();
+        std::fs::write(&uncompressed, RAW_FILE_UNCOMPRESSED).unwrap();
+        std::fs::write(&empty, b"").unwrap();
+
+        // SAFETY: the files are only written above
+        unsafe {
+            assert_eq!(parse_mmap(&compressed).unwrap(), parse(RAW_FILE).unwrap());
+            assert_eq!(
+                parse_mmap(&uncompressed).unwrap(),
+                parse(RAW_FILE_UNCOMPRESSED).unwrap()
+            );
+            assert!(matches!(
+                parse_mmap(&empty),
+                Err(SARError::InvalidFileHeader)
+            ));
+            assert!(matches!(
+                parse_mmap(dir.join("missing.sar")),
+                Err(SARError::IoError(_))
+            ));
+        }
+        // The map is private, so decrypting in place leaves the file untouched
+        assert_eq!(std::fs::read(&compressed).unwrap(), RAW_FILE);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_parse_file_async() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../fixture/sa0a1d081b8a108bb8c9847c4cd83db662.sar");
+
         assert_eq!(
-            payload.name,
-            vec![84, 104, 97, 110, 107, 32, 121, 111, 117, 32, 33, 33]
+            parse_file_async(&path).await.unwrap(),
+            parse_file(&path).unwrap()
         );
     }
+
+    #[test]
+    fn test_write() {
+        let payload = parse(RAW_FILE).unwrap();
+
+        let bytes = write(&payload).unwrap();
+        let written = parse(bytes).unwrap();
+
+        assert_eq!(written, payload);
+    }
+
+    #[test]
+    fn test_write_too_many_layers() {
+        let layer = MockLayer::new((0, 0), (1, 1), 1, sa::Color::new(255, 0, 0, 0));
+        let sa = MockSymbolArt::with_layers(vec![layer; 256]);
+
+        assert!(matches!(write(&sa), Err(SARError::TooManyLayers(256, 255))));
+    }
+
+    #[test]
+    fn test_parse_any() {
+        let expected = parse(RAW_FILE).unwrap();
+        assert_eq!(parse_any(RAW_FILE).unwrap(), expected);
+
+        // A wrapper header, containing the magic by chance
+        let mut wrapped = b"ICE\0sar\x04".to_vec();
+        wrapped.resize(64, 0xab);
+        wrapped.extend_from_slice(RAW_FILE);
+        assert_eq!(parse_any(wrapped.clone()).unwrap(), expected);
+
+        for bytes in [RAW_FILE, &wrapped] {
+            let mut encoder = ages_prs::ModernPrsEncoder::new(Vec::new());
+            std::io::Write::write_all(&mut encoder, bytes).unwrap();
+            let packed = encoder.into_inner().unwrap();
+            assert_eq!(parse_any(packed).unwrap(), expected);
+        }
+
+        for bytes in [&b""[..], b"sar", b"nope", &[0xab; 64]] {
+            assert!(matches!(parse_any(bytes), Err(SARError::InvalidFileHeader)));
+        }
+
+        // A file of an unknown size parses leniently, but its size can't be read
+        let mut unsized_file = write(&MockSymbolArt::with_layers(vec![])).unwrap();
+        decode::decrypt(&mut unsized_file[4..]);
+        unsized_file[4 + 5] = 50;
+        decode::encrypt(&mut unsized_file[4..]);
+        assert!(parse(unsized_file.clone()).is_ok());
+        assert!(matches!(
+            parse_any(unsized_file),
+            Err(SARError::InvalidFileHeader)
+        ));
+        assert!(matches!(
+            parse(&b"sa"[..]),
+            Err(SARError::InvalidFileHeader)
+        ));
+        assert!(matches!(
+            parse(&b"sar\x04"[..]),
+            Err(SARError::InvalidFileHeader)
+        ));
+    }
+
+    #[test]
+    fn test_parse_strict() {
+        assert_eq!(parse_strict(RAW_FILE).unwrap(), parse(RAW_FILE).unwrap());
+
+        let layer = MockLayer::new((0, 0), (1, 1), 1, sa::Color::new(255, 0, 0, 0));
+        let mut sa = MockSymbolArt::with_layers(vec![layer; 226]);
+        assert!(matches!(
+            parse_strict(write(&sa).unwrap()),
+            Err(SARError::TooManyLayers(226, 225))
+        ));
+
+        sa.layers.truncate(2);
+        sa.layers[1].symbol_id = 1000;
+        assert!(matches!(
+            parse_strict(write(&sa).unwrap()),
+            Err(SARError::InvalidLayerSymbol(1, id)) if id.id() == 1000
+        ));
+        // Neither 0 nor the gap between the alphabet and the shapes has symbols
+        for unknown in [0, 100] {
+            sa.layers[1].symbol_id = unknown;
+            assert!(matches!(
+                parse_strict(write(&sa).unwrap()),
+                Err(SARError::InvalidLayerSymbol(1, id)) if id.id() == unknown
+            ));
+        }
+
+        // Drop the last block of the second layer, keeping the cipher aligned
+        sa.layers[1].symbol_id = 1;
+        let mut bytes = write(&sa).unwrap();
+        bytes.truncate(bytes.len() - 8);
+        assert!(matches!(
+            parse_strict(bytes),
+            Err(SARError::TruncatedLayers(2, 1))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let payload = parse(RAW_FILE).unwrap();
+
+        let json = serde_json::to_string(&payload).unwrap();
+        let deserialized: Payload = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized, payload);
+    }
 }
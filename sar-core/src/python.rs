@@ -0,0 +1,160 @@
+//! Bindings exposing the parser and the renderer as the `sar` Python module
+//!
+//! `draw` releases the GIL while rendering, so SymbolArts drawn from several
+//! Python threads are rendered in parallel.
+
+use std::sync::OnceLock;
+
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+
+use crate::{Layer, Payload, SymbolArt as _, SymbolArtDrawer, SymbolArtLayer as _};
+
+/// A parsed SymbolArt
+#[pyclass(name = "SymbolArt", module = "sar", frozen)]
+pub struct PySymbolArt {
+    payload: Payload,
+}
+
+#[pymethods]
+impl PySymbolArt {
+    #[getter]
+    fn name(&self) -> &str {
+        self.payload.name()
+    }
+
+    #[getter]
+    fn author_id(&self) -> u32 {
+        self.payload.author_id()
+    }
+
+    #[getter]
+    fn width(&self) -> u8 {
+        self.payload.width()
+    }
+
+    #[getter]
+    fn height(&self) -> u8 {
+        self.payload.height()
+    }
+
+    /// The sound effect id, or `None` when the SymbolArt is silent
+    #[getter]
+    fn sound_effect(&self) -> Option<u8> {
+        self.payload.sound_effect().map(|sound| sound.id())
+    }
+
+    /// The layers from the top to the bottom, hidden ones included
+    #[getter]
+    fn layers(&self) -> Vec<PyLayer> {
+        self.payload.layers().iter().map(PyLayer::from).collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "SymbolArt(name={:?}, width={}, height={}, layers={})",
+            self.payload.name(),
+            self.payload.width(),
+            self.payload.height(),
+            self.payload.layers().len()
+        )
+    }
+}
+
+/// A layer of a SymbolArt, with its corners in SymbolArt coordinates
+#[pyclass(name = "Layer", module = "sar", frozen, get_all)]
+pub struct PyLayer {
+    symbol_id: u32,
+    /// The color as an `(r, g, b, a)` tuple
+    color: (u8, u8, u8, u8),
+    alpha: f32,
+    hidden: bool,
+    top_left: (u8, u8),
+    top_right: (u8, u8),
+    bottom_left: (u8, u8),
+    bottom_right: (u8, u8),
+}
+
+impl From<&Layer> for PyLayer {
+    fn from(layer: &Layer) -> Self {
+        let color = layer.color();
+        let corner = |p: crate::core::sa::Position| (p.x, p.y);
+        Self {
+            symbol_id: layer.symbol().id().id(),
+            color: (color.r, color.g, color.b, color.a),
+            alpha: layer.alpha(),
+            hidden: layer.is_hidden(),
+            top_left: corner(layer.top_left()),
+            top_right: corner(layer.top_right()),
+            bottom_left: corner(layer.bottom_left()),
+            bottom_right: corner(layer.bottom_right()),
+        }
+    }
+}
+
+#[pymethods]
+impl PyLayer {
+    fn __repr__(&self) -> String {
+        format!(
+            "Layer(symbol_id={}, color={:?}, hidden={})",
+            self.symbol_id,
+            self.color,
+            if self.hidden { "True" } else { "False" }
+        )
+    }
+}
+
+/// Parses the content of a SAR file, raising `ValueError` if it is malformed
+#[pyfunction]
+fn parse(bytes: &[u8]) -> PyResult<PySymbolArt> {
+    let payload = crate::parse(bytes.to_vec()).map_err(to_py_error)?;
+    Ok(PySymbolArt { payload })
+}
+
+/// Draws the SymbolArt at the given scale and encodes it as PNG
+///
+/// The drawer and its symbol sheets are created on the first call and shared
+/// by every thread afterwards.
+#[pyfunction]
+#[pyo3(signature = (sa, scale = 1.0))]
+fn draw<'py>(py: Python<'py>, sa: &PySymbolArt, scale: f32) -> PyResult<Bound<'py, PyBytes>> {
+    static DRAWER: OnceLock<SymbolArtDrawer> = OnceLock::new();
+
+    let png = py.detach(|| {
+        let drawer = DRAWER.get_or_init(SymbolArtDrawer::new);
+        drawer.draw_png(&sa.payload, scale)
+    });
+
+    Ok(PyBytes::new(py, &png.map_err(to_py_error)?))
+}
+
+fn to_py_error(e: crate::core::result::SARError) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+#[pymodule(name = "sar")]
+mod sar {
+    #[pymodule_export]
+    use super::{draw, parse, PyLayer, PySymbolArt};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::RAW_FILE;
+
+    #[test]
+    fn test_parse_and_draw() {
+        Python::initialize();
+        Python::attach(|py| {
+            let sa = parse(RAW_FILE).unwrap();
+            assert_eq!((sa.width(), sa.height()), (193, 96));
+            assert_eq!(sa.layers().len(), sa.payload.layers().len());
+
+            let png = draw(py, &sa, 0.5).unwrap();
+            let image = image::load_from_memory(png.as_bytes()).unwrap();
+            assert_eq!((image.width(), image.height()), (96, 48));
+
+            assert!(parse(&[1, 2, 3]).is_err());
+        });
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use image::RgbaImage;
+use imageproc::geometric_transformations::Projection;
+
+use crate::{
+    core::sa::{content_bytes, SymbolArt, SymbolArtLayer},
+    Result,
+};
+
+/// The projections kept before the cache is cleared, about 100 bytes each
+pub(crate) const MAX_CACHED_PROJECTIONS: usize = 16_384;
+
+/// Identifies a projection by the layer corners, the symbol size, the scale and
+/// the canvas offset
+///
+/// The corners are part of the key so that layers of different SymbolArts drawn
+/// with the same drawer never share a projection by mistake.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct ProjectionKey {
+    corners: [(u8, u8); 4],
+    symbol_size: (u32, u32),
+    scale: u32,
+    offset: (u32, u32),
+}
+
+impl ProjectionKey {
+    pub(crate) fn new(
+        corners: [(u8, u8); 4],
+        symbol_size: (u32, u32),
+        scale: f32,
+        offset: (f32, f32),
+    ) -> Self {
+        Self {
+            corners,
+            symbol_size,
+            scale: scale.to_bits(),
+            offset: (offset.0.to_bits(), offset.1.to_bits()),
+        }
+    }
+}
+
+/// A cache of layer projections, shared by the threads drawing the chunks
+///
+/// Clones share the projections, which don't depend on how the drawer is configured.
+#[derive(Clone, Default)]
+pub(crate) struct ProjectionCache {
+    projections: Arc<Mutex<HashMap<ProjectionKey, Projection>>>,
+}
+
+impl ProjectionCache {
+    /// Returns the cached projection, or computes and caches it
+    ///
+    /// Failed projections are not cached. Once `MAX_CACHED_PROJECTIONS` is
+    /// reached the cache starts over, which bounds its memory.
+    pub(crate) fn get_or_insert_with<F>(&self, key: ProjectionKey, f: F) -> Result<Projection>
+    where
+        F: FnOnce() -> Result<Projection>,
+    {
+        // A poisoned lock only means another thread panicked while inserting
+        let cached = self
+            .projections
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&key)
+            .copied();
+        if let Some(projection) = cached {
+            return Ok(projection);
+        }
+
+        let projection = f()?;
+        let mut projections = self.projections.lock().unwrap_or_else(|e| e.into_inner());
+        if projections.len() >= MAX_CACHED_PROJECTIONS {
+            projections.clear();
+        }
+        projections.insert(key, projection);
+
+        Ok(projection)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.projections
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+}
+
+/// Identifies a drawn image by the content of the SymbolArt and the scale
+///
+/// The content is every property of the art that changes how it is drawn, so
+/// two arts with the same key are drawn identically. The name and the author
+/// are left out, which lets copies of an art share their image.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ImageKey {
+    content: Vec<u8>,
+    scale: u32,
+}
+
+impl ImageKey {
+    pub(crate) fn new<S, L>(sa: &S, scale: f32) -> Self
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer,
+    {
+        Self {
+            content: content_bytes(sa),
+            scale: scale.to_bits(),
+        }
+    }
+}
+
+/// A cache of drawn images, evicting the least recently used once full
+pub(crate) struct ImageCache {
+    capacity: usize,
+    entries: Mutex<ImageEntries>,
+}
+
+/// Clones start out empty, as the images depend on the configuration of the
+/// drawer, which the clone may change
+impl Clone for ImageCache {
+    fn clone(&self) -> Self {
+        Self::new(self.capacity)
+    }
+}
+
+#[derive(Default)]
+struct ImageEntries {
+    /// The images with the tick they were last used at
+    images: HashMap<ImageKey, (Arc<RgbaImage>, u64)>,
+    tick: u64,
+}
+
+impl ImageCache {
+    /// Creates a cache holding up to `capacity` images, caching nothing if zero
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Returns the cached image, or draws and caches it
+    ///
+    /// The lock isn't held while drawing, so threads missing the same key at
+    /// once each draw the image. Failed drawings are not cached.
+    pub(crate) fn get_or_insert_with<F>(&self, key: ImageKey, f: F) -> Result<Arc<RgbaImage>>
+    where
+        F: FnOnce() -> Result<RgbaImage>,
+    {
+        {
+            let mut entries = self.lock();
+            entries.tick += 1;
+            let tick = entries.tick;
+            if let Some((image, used)) = entries.images.get_mut(&key) {
+                *used = tick;
+                return Ok(image.clone());
+            }
+        }
+
+        let image = Arc::new(f()?);
+        if self.capacity == 0 {
+            return Ok(image);
+        }
+
+        let mut entries = self.lock();
+        if entries.images.len() >= self.capacity && !entries.images.contains_key(&key) {
+            let oldest = entries
+                .images
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.images.remove(&oldest);
+            }
+        }
+        entries.tick += 1;
+        let tick = entries.tick;
+        entries.images.insert(key, (image.clone(), tick));
+
+        Ok(image)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ImageEntries> {
+        // A poisoned lock only means another thread panicked while inserting
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
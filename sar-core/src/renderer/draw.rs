@@ -1,15 +1,42 @@
 use crate::{core::sa::Color, Result};
-use image::{imageops, GenericImage, ImageBuffer, Pixel, Rgba, RgbaImage};
-use imageproc::geometric_transformations::Projection;
-use std::sync::mpsc;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use image::{
+    codecs::{
+        gif::{GifEncoder, Repeat},
+        png::PngEncoder,
+    },
+    imageops, Delay, Frame, GenericImageView, ImageBuffer, ImageEncoder, Pixel, Rgb, RgbImage,
+    Rgba, RgbaImage,
+};
+use imageproc::{
+    geometric_transformations::{Interpolation, Projection},
+    rect::Rect,
+};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    ops::{ControlFlow, Range},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+};
 
 use crate::core::{
     result::SARError,
-    sa::{SymbolArt, SymbolArtLayer},
+    sa::{BlendMode, Position, SymbolArt, SymbolArtLayer, MAX_LAYERS},
+    symbol::SymbolId,
 };
 use rayon::prelude::*;
 
-use super::resource::{self};
+#[cfg(feature = "gpu")]
+use super::gpu;
+use super::{
+    cache::{ImageCache, ImageKey, ProjectionCache, ProjectionKey},
+    effect,
+    resource::{self},
+    scratch::ScratchPool,
+};
 
 /// A trait defining the core rendering capabilities for SymbolArt compositions
 pub trait Drawer<S, L>
@@ -19,6 +46,107 @@ where
 {
     fn draw(&self, sa: &S) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>>;
     fn draw_with_scale(&self, sa: &S, scale: f32) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>>;
+    /// Draws the SymbolArt onto an existing buffer, using its dimensions as the canvas size
+    ///
+    /// The buffer is cleared with the background color first and the SymbolArt is
+    /// drawn centered on it at scale 1.0, without cropping to the view size.
+    fn draw_into(&self, sa: &S, target: &mut RgbaImage) -> Result<()>;
+}
+
+/// The number of layers left out of a render, by reason
+///
+/// Returned by `SymbolArtDrawer::draw_with_report`. Hidden layers are left out
+/// on purpose, and off-canvas layers are drawn but can't be seen, while the
+/// other counts are layers that failed to draw and were suppressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderReport {
+    /// Layers skipped because they are hidden
+    pub hidden: usize,
+    /// Layers skipped because their symbol isn't in the resources
+    pub missing_symbol: usize,
+    /// Layers skipped because their corners couldn't be projected
+    pub projection_failure: usize,
+    /// Layers drawn entirely outside of the output, see `SymbolArtDrawer::off_canvas_layers`
+    pub off_canvas: usize,
+}
+
+impl RenderReport {
+    /// Whether any layer that should have been drawn was left out
+    pub fn is_degraded(&self) -> bool {
+        self.missing_symbol > 0 || self.projection_failure > 0
+    }
+
+    fn record(&mut self, e: &SARError) {
+        match e {
+            SARError::SymbolNotFound(_) => self.missing_symbol += 1,
+            SARError::ProjectionError(..) => self.projection_failure += 1,
+            _ => {}
+        }
+    }
+}
+
+/// Which end of `SymbolArt::layers` is the top of the composition
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LayerOrder {
+    /// The first layer is drawn on top of the others, as in SAR files
+    #[default]
+    TopFirst,
+    /// The first layer is drawn beneath the others
+    BottomFirst,
+}
+
+/// The order of the channels in the bytes returned by `SymbolArtDrawer::draw_raw`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelOrder {
+    /// Red, green, blue and alpha, the layout of `RgbaImage`
+    #[default]
+    Rgba,
+    /// Blue, green, red and alpha, as many GPU texture formats expect
+    Bgra,
+}
+
+/// Moves the corners of a layer for `SymbolArtDrawer::draw_with_overrides`
+///
+/// The corners are scaled and rotated around the center of the layer, then
+/// translated, all in SymbolArt coordinates. The default leaves them as they are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerTransform {
+    /// The distance to move the layer by
+    pub translate: (f32, f32),
+    /// The clockwise rotation in degrees
+    pub rotate: f32,
+    /// The factors to stretch the layer by
+    pub scale: (f32, f32),
+}
+
+impl Default for LayerTransform {
+    fn default() -> Self {
+        Self {
+            translate: (0.0, 0.0),
+            rotate: 0.0,
+            scale: (1.0, 1.0),
+        }
+    }
+}
+
+impl LayerTransform {
+    /// Applies the transform to the corners of a layer, in the order of `SymbolArtLayer::corners`
+    pub fn apply(&self, corners: [Position; 4]) -> [(f32, f32); 4] {
+        let corners = corners.map(|p| (p.x as f32, p.y as f32));
+        let center = corners
+            .iter()
+            .fold((0.0, 0.0), |(x, y), p| (x + p.0 / 4.0, y + p.1 / 4.0));
+        let (sin, cos) = self.rotate.to_radians().sin_cos();
+
+        corners.map(|(x, y)| {
+            let x = (x - center.0) * self.scale.0;
+            let y = (y - center.1) * self.scale.1;
+            (
+                x * cos - y * sin + center.0 + self.translate.0,
+                x * sin + y * cos + center.1 + self.translate.1,
+            )
+        })
+    }
 }
 
 /// A drawer that renders SymbolArt compositions into images
@@ -46,13 +174,48 @@ where
 ///
 /// The drawer can be configured with various options:
 /// - `with_raise_error`: Controls whether rendering errors should be raised or suppressed
-/// - Canvas size: Default is 256x256 pixels
+/// - `with_canvas_size`: Sets the canvas size before scaling (default: 256x256)
 /// - `with_chunk_size`: Controls parallel processing of layers (default: 10)
+/// - `with_chunk_size_auto`: Splits the layers evenly across the rayon threads instead
+/// - `with_single_threaded`: Draws the chunks one after another on the calling thread
+/// - `with_interpolation`: Selects how symbols are sampled when warped (default: Nearest)
+/// - `with_supersample`: Draws at a multiple of the scale and downsamples the output (default: 1)
+/// - `with_background`: Sets the color the layers are composited onto (default: transparent)
+/// - `with_auto_crop`: Crops the output to the drawn pixels instead of the view size
+/// - `with_padding`: Adds a border around the output (default: 0)
+/// - `with_flip`: Mirrors the output horizontally and/or vertically
+/// - `with_grayscale`: Desaturates the output to its luminance
+/// - `with_tint`: Multiplies the colors of the output (default: white)
+/// - `with_checkerboard`: Shows the output over a gray checkerboard (default: disabled)
+/// - `with_quantize`: Reduces the output to a number of colors (default: disabled)
+/// - `with_premultiplied_alpha`: Outputs the colors multiplied by their alpha (default: disabled)
+/// - `with_max_fit_scale`: Caps the scale `draw_fit` enlarges arts by (default: unlimited)
+/// - `with_layer_order`: Sets whether the first layer is the top or the bottom one (default: top)
+/// - `with_max_layers`: Rejects arts with more layers before drawing them (default: 225)
+/// - `with_include_hidden`: Draws the hidden layers as well (default: disabled)
+/// - `with_hidden_alpha`: Scales the alpha of the hidden layers when drawn (default: 1.0)
+/// - `with_symbol_override`: Draws a symbol with another image than the resource's
+/// - `with_gpu`: Renders the layers with wgpu, behind the `gpu` feature
+/// - `with_projection_cache`: Reuses the layer projections across renders (default: enabled)
+/// - `with_cache_capacity`: Sets how many images `draw_cached` keeps (default: 32)
 ///
 /// # Performance
 ///
 /// The drawer uses parallel processing to render layers efficiently. The chunk size
 /// can be adjusted to balance between parallelization overhead and throughput.
+/// Layers with a `BlendMode` other than `Normal` are blended with every layer
+/// beneath them, so an art using one is drawn in a single chunk on the CPU.
+///
+/// With the `tracing` feature, every render is recorded as a `draw` span with
+/// the layer count and scale. The chunks, the `projection` and `warp` of every
+/// layer, and the final `composite` are spans inside it.
+///
+/// # Empty SymbolArts
+///
+/// An art without visible layers is drawn as an image of its view size filled
+/// with the background color, transparent by default. A view narrower or lower
+/// than a pixel, like that of an art without a size, is drawn 1 pixel wide or
+/// high, so the image can always be encoded.
 ///
 /// # Error Handling
 ///
@@ -63,24 +226,95 @@ where
 ///
 /// The drawer maintains a cache of symbol resources to improve rendering performance.
 /// These resources are loaded when the drawer is created and shared across all
-/// rendering operations.
+/// rendering operations. The canvas sized buffers the symbols are warped into are
+/// kept as well, one per layer being drawn at the same time, so they are only
+/// allocated again when the canvas size changes.
+///
+/// Cloning a drawer is cheap: the clone shares the symbol resources, the GPU
+/// device and the projection cache, and starts with its own empty
+/// `draw_cached` cache and buffers. It can be configured further without
+/// affecting the original.
+#[derive(Clone)]
 pub struct SymbolArtDrawer {
     resource: resource::Resource,
     canvas_size: (u32, u32),
-    chunk_size: usize,
+    chunk_size: ChunkSize,
+    single_threaded: bool,
     suppress_failure: bool,
+    background: Rgba<u8>,
+    interpolation: Interpolation,
+    supersample: u32,
+    auto_crop: bool,
+    padding: u32,
+    flip: (bool, bool),
+    grayscale: bool,
+    tint: Rgba<u8>,
+    checkerboard: Option<u32>,
+    quantize: Option<usize>,
+    premultiplied_alpha: bool,
+    max_fit_scale: f32,
+    max_layers: usize,
+    layer_order: LayerOrder,
+    include_hidden: bool,
+    hidden_alpha: f32,
+    symbol_overrides: HashMap<SymbolId, Arc<RgbaImage>>,
+    #[cfg(feature = "gpu")]
+    gpu: Option<gpu::GpuRenderer>,
+    projection_cache: Option<ProjectionCache>,
+    image_cache: ImageCache,
+    scratch: ScratchPool,
 }
 
 impl SymbolArtDrawer {
+    /// Creates a drawer with the bundled symbol resources
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bundled resources can't be loaded, use `try_new` to handle
+    /// the error instead.
     pub fn new() -> Self {
-        let resource = resource::Resource::new().unwrap();
+        Self::try_new().expect("failed to load the bundled symbol resources")
+    }
+
+    /// Creates a drawer with the bundled symbol resources, failing if they can't be loaded
+    pub fn try_new() -> Result<Self> {
+        Ok(Self::with_resource(resource::Resource::new()?))
+    }
+
+    /// Creates a drawer that renders symbols from the given resource
+    ///
+    /// Use `Resource::from_dir` to render with a custom symbol pack.
+    pub fn with_resource(resource: resource::Resource) -> Self {
         let canvas_size = (256, 256);
 
         Self {
             resource,
             canvas_size,
-            chunk_size: 10,
+            chunk_size: ChunkSize::Fixed(10),
+            single_threaded: false,
             suppress_failure: true,
+            background: Rgba([0; 4]),
+            interpolation: Interpolation::Nearest,
+            supersample: 1,
+            auto_crop: false,
+            padding: 0,
+            flip: (false, false),
+            grayscale: false,
+            tint: Rgba([255; 4]),
+            checkerboard: None,
+            quantize: None,
+            premultiplied_alpha: false,
+            max_fit_scale: f32::INFINITY,
+            max_layers: MAX_LAYERS,
+            layer_order: LayerOrder::TopFirst,
+            include_hidden: false,
+            hidden_alpha: 1.0,
+            symbol_overrides: HashMap::new(),
+            #[cfg(feature = "gpu")]
+            gpu: None,
+            projection_cache: Some(ProjectionCache::default()),
+            image_cache: ImageCache::new(DEFAULT_IMAGE_CACHE_CAPACITY),
+            scratch: ScratchPool::default(),
         }
     }
 
@@ -90,222 +324,3763 @@ impl SymbolArtDrawer {
     }
 
     pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
-        self.chunk_size = chunk_size;
+        self.chunk_size = ChunkSize::Fixed(chunk_size);
+        self
+    }
+
+    /// Picks the chunk size at render time so every rayon thread gets one chunk
+    ///
+    /// The layers are split into `ceil(layer_count / rayon::current_num_threads())`
+    /// sized chunks, but never smaller than `MIN_AUTO_CHUNK_SIZE` layers, as every
+    /// chunk costs a canvas sized overlay to allocate and composite.
+    pub fn with_chunk_size_auto(mut self) -> Self {
+        self.chunk_size = ChunkSize::Auto;
+        self
+    }
+
+    /// Draws the chunks and composites the rows sequentially instead of with rayon
+    ///
+    /// The layers are still split with `with_chunk_size`, so the output is the
+    /// same as in parallel, and no thread pool is started. With
+    /// `with_chunk_size_auto` there is one thread to split the layers for.
+    pub fn with_single_threaded(mut self, enabled: bool) -> Self {
+        self.single_threaded = enabled;
+        self
+    }
+
+    /// Sets the color the layers are composited onto
+    ///
+    /// The canvas is filled with this color before any layer is drawn, so a
+    /// semi-transparent background shows through the gaps between layers.
+    /// Symbols are still warped onto a transparent buffer, as filling it would
+    /// paint the layer color over the whole canvas.
+    pub fn with_background(mut self, color: Rgba<u8>) -> Self {
+        self.background = color;
+        self
+    }
+
+    /// Sets the interpolation used when warping symbols onto the canvas
+    ///
+    /// `Interpolation::Nearest` is the fastest and the default. `Bilinear` and
+    /// `Bicubic` produce smoother edges on rotated and scaled symbols, as the
+    /// symbol alpha is then used as coverage for the layer color. Symbols drawn
+    /// with their own colors blend their edges with the nearest drawn color
+    /// rather than with transparent black, so they don't get a dark halo.
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Draws the layers at `factor` times the scale, then downsamples them to the scale
+    ///
+    /// The downsampling uses a Lanczos3 filter, which smooths the edges of the
+    /// symbols more than `with_interpolation` does. The canvas has `factor²`
+    /// times the pixels, and drawing takes about as much longer, so the factor
+    /// is clamped to `1..=4`, 1 drawing at the scale directly. `draw_into`
+    /// doesn't supersample, as it draws into the given buffer.
+    pub fn with_supersample(mut self, factor: u32) -> Self {
+        self.supersample = factor.clamp(1, MAX_SUPERSAMPLE);
+        self
+    }
+
+    /// Crops the output to the smallest rectangle containing the drawn pixels
+    ///
+    /// Pixels that still have the background color are not considered drawn.
+    /// The whole canvas is scanned, so layers outside of the view size are kept.
+    /// If nothing was drawn, the output is a single pixel of the background color.
+    pub fn with_auto_crop(mut self, enabled: bool) -> Self {
+        self.auto_crop = enabled;
+        self
+    }
+
+    /// Adds a border of `pixels` around the output, filled with the background color
+    ///
+    /// The padding is in output pixels, so it does not grow with the scale.
+    pub fn with_padding(mut self, pixels: u32) -> Self {
+        self.padding = pixels;
+        self
+    }
+
+    /// Mirrors the output image horizontally and/or vertically
+    ///
+    /// The flip is applied to the final image, so it doesn't affect how layers
+    /// are projected onto the canvas.
+    pub fn with_flip(mut self, horizontal: bool, vertical: bool) -> Self {
+        self.flip = (horizontal, vertical);
+        self
+    }
+
+    /// Converts the output image to its luminance, keeping the alpha
+    ///
+    /// This is a separate pass on the final image, so layers are blended exactly
+    /// as they would be in color.
+    pub fn with_grayscale(mut self, enabled: bool) -> Self {
+        self.grayscale = enabled;
+        self
+    }
+
+    /// Multiplies the color channels of the output image by the tint
+    ///
+    /// Each channel of the tint is normalized to a factor between 0.0 and 1.0,
+    /// so `Rgba([255, 0, 0, 255])` keeps only the red channel and white leaves
+    /// the image unchanged. The alpha of the output and of the tint are ignored.
+    pub fn with_tint(mut self, tint: Rgba<u8>) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    /// Composites the output image over a gray checkerboard of `cell` pixel squares
+    ///
+    /// The transparent parts of the output then show like in image editors, so
+    /// this is meant for previews: the output is opaque and can't be composited
+    /// onto anything else. It follows every other color pass, covers the space
+    /// `draw_fit` leaves around the art, and its cells line up across the tiles
+    /// of `draw_tile`. A `cell` of 0 disables it.
+    pub fn with_checkerboard(mut self, cell: u32) -> Self {
+        self.checkerboard = (cell > 0).then_some(cell);
+        self
+    }
+
+    /// Reduces the output image to at most `max_colors` distinct colors
+    ///
+    /// The palette is learnt from the output with NeuQuant, so the same art
+    /// always comes out the same, and outputs with few enough colors are kept
+    /// as they are. Fully transparent pixels stay transparent and take one of
+    /// the colors, the alpha of the others is quantized with their color. This
+    /// runs after the checkerboard and before premultiplying the alpha, and is
+    /// ignored by `draw_tile` as it depends on the whole image. A `max_colors`
+    /// of 0 disables it.
+    pub fn with_quantize(mut self, max_colors: usize) -> Self {
+        self.quantize = (max_colors > 0).then_some(max_colors);
+        self
+    }
+
+    /// Multiplies the color channels of the output image by its alpha
+    ///
+    /// This is the very last pass, after the layers are blended in straight alpha
+    /// and every other effect is applied, so it only changes how the output is
+    /// encoded. Use it when the image is composited by something expecting
+    /// premultiplied alpha, which shows dark fringes with straight alpha. The
+    /// space `draw_fit` leaves around the art is premultiplied as well.
+    pub fn with_premultiplied_alpha(mut self, enabled: bool) -> Self {
+        self.premultiplied_alpha = enabled;
+        self
+    }
+
+    /// Sets how many layers an art may have before drawing it fails
+    ///
+    /// Arts with more layers fail with `SARError::TooManyLayers` before any canvas
+    /// is allocated, which bounds the work done for untrusted files. The default
+    /// is the 225 layers PSO2 allows.
+    pub fn with_max_layers(mut self, max: usize) -> Self {
+        self.max_layers = max;
+        self
+    }
+
+    /// Sets whether the first layer of `SymbolArt::layers` is the top or the bottom one
+    ///
+    /// SAR files list their layers from the top, which `LayerOrder::TopFirst`
+    /// expects. Errors and `draw_layers` still refer to the layers by their
+    /// index in `layers`.
+    pub fn with_layer_order(mut self, order: LayerOrder) -> Self {
+        self.layer_order = order;
+        self
+    }
+
+    /// Sets whether hidden layers are drawn like the visible ones
+    ///
+    /// This shows what an art looks like with every layer revealed, which helps
+    /// when editing it. Hidden layers keep their place in the stacking order.
+    pub fn with_include_hidden(mut self, include: bool) -> Self {
+        self.include_hidden = include;
+        self
+    }
+
+    /// Multiplies the alpha of hidden layers drawn with `with_include_hidden`
+    ///
+    /// Values below 1.0 fade the hidden layers, so they stand apart from the
+    /// visible ones. The value is clamped to `0.0..=1.0`.
+    pub fn with_hidden_alpha(mut self, alpha: f32) -> Self {
+        self.hidden_alpha = alpha.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Draws the symbol `id` with `image` instead of the one in the resource
+    ///
+    /// The image is colored like the symbol it replaces: the symbols of the
+    /// Color sheet keep their own colors, and only the alpha of the other ones
+    /// is filled with the layer color. The image doesn't have to be square: all
+    /// of it is stretched onto the layer, whatever its dimensions. Layers using
+    /// an overridden symbol are drawn on the CPU.
+    pub fn with_symbol_override(mut self, id: SymbolId, mut image: RgbaImage) -> Self {
+        if resource::Resource::is_colored(id) {
+            // Like the bundled symbols, see `Resource::get_rgba_image`
+            effect::bleed(&mut image, 2);
+        }
+
+        self.symbol_overrides.insert(id, Arc::new(image));
+        self
+    }
+
+    /// Caps the scale `draw_fit` uses, so that small arts aren't enlarged past it
+    ///
+    /// Arts that need a smaller scale to fit are still scaled down.
+    pub fn with_max_fit_scale(mut self, max_scale: f32) -> Self {
+        self.max_fit_scale = max_scale;
+        self
+    }
+
+    /// Sets the size of the canvas the layers are drawn onto, before scaling
+    ///
+    /// The SymbolArt stays centered on the canvas, so a larger canvas leaves room
+    /// for layers placed outside of the default 256x256 viewport. A canvas smaller
+    /// than the SymbolArt itself is grown to fit it.
+    /// Both dimensions must be non-zero, otherwise drawing fails with
+    /// `SARError::InvalidCanvasSize`.
+    pub fn with_canvas_size(mut self, width: u32, height: u32) -> Self {
+        self.canvas_size = (width, height);
+        self
+    }
+
+    /// Renders the layers on the GPU when an adapter is available
+    ///
+    /// The symbol sheets are uploaded once, and every layer is then drawn in a
+    /// single pass. The output matches the CPU renderer up to rounding. Only the
+    /// `Nearest` interpolation is supported on the GPU, other interpolations and
+    /// canvases larger than the device limits are rendered on the CPU, as is
+    /// everything when no adapter is found. Use `uses_gpu` to check which one
+    /// was picked.
+    #[cfg(feature = "gpu")]
+    pub fn with_gpu(mut self) -> Self {
+        self.gpu = gpu::GpuRenderer::new(&self.resource);
+        self
+    }
+
+    /// The width and height of the image the symbol `id` is drawn with
+    ///
+    /// The symbols of the resource are square, with a size that depends on their
+    /// sheet, while an override from `with_symbol_override` keeps the dimensions
+    /// of its image. Returns `None` for ids that have neither.
+    pub fn symbol_dimensions(&self, id: SymbolId) -> Option<(u32, u32)> {
+        match self.symbol_overrides.get(&id) {
+            Some(image) => Some(image.dimensions()),
+            None => self
+                .resource
+                .symbol_pixels(id)
+                .map(|pixels| (pixels, pixels)),
+        }
+    }
+
+    /// Whether `with_gpu` found an adapter to render with
+    #[cfg(feature = "gpu")]
+    pub fn uses_gpu(&self) -> bool {
+        self.gpu.is_some()
+    }
+
+    /// Keeps the projection of every layer drawn, to reuse it on the next renders
+    ///
+    /// Projections are keyed by the layer corners, the scale and the canvas
+    /// offset, so drawing the same SymbolArt again at a scale it was already drawn
+    /// at skips solving them. Each entry takes about 100 bytes, and the cache is
+    /// cleared once it holds `MAX_CACHED_PROJECTIONS` (16384) entries, so it stays
+    /// under 2 MB. Disable it when the drawer renders many different
+    /// SymbolArts only once, where it would never be hit.
+    pub fn with_projection_cache(mut self, enabled: bool) -> Self {
+        self.projection_cache = enabled.then(ProjectionCache::default);
         self
     }
 
-    fn calc_canvas_size(&self, scale: f32) -> (u32, u32) {
+    /// Sets how many images `draw_cached` keeps, zero disabling the cache
+    ///
+    /// Once full, the least recently drawn image is evicted. The cached images
+    /// are dropped, and since they were drawn with the settings of the drawer
+    /// at the time, the cache should be sized after configuring the rest.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.image_cache = ImageCache::new(capacity);
+        self
+    }
+
+    fn calc_canvas_size(&self, scale: f32) -> Result<(u32, u32)> {
+        let (width, height) = self.canvas_size;
+        if width == 0 || height == 0 {
+            return Err(SARError::InvalidCanvasSize(width, height));
+        }
+
+        Ok((
+            (width as f32 * scale) as u32,
+            (height as f32 * scale) as u32,
+        ))
+    }
+
+    /// Calculates the offset to move the SymbolArt origin to the center of the canvas
+    fn calc_canvas_offset(canvas_size: (u32, u32), scale: f32) -> (f32, f32) {
         (
-            (self.canvas_size.0 as f32 * scale) as u32,
-            (self.canvas_size.1 as f32 * scale) as u32,
+            canvas_size.0 as f32 / 2.0 - SA_CENTER * scale,
+            canvas_size.1 as f32 / 2.0 - SA_CENTER * scale,
         )
     }
 
+    /// The size of the drawn view, at least 1x1 so that an art without a size
+    /// or drawn at a tiny scale still gives an image to encode
     fn calc_view_size<S>(sa: &S, scale: f32) -> (u32, u32)
     where
         S: SymbolArt,
     {
         (
-            (sa.width() as f32 * scale) as u32,
-            (sa.height() as f32 * scale) as u32,
+            ((sa.width() as f32 * scale) as u32).max(1),
+            ((sa.height() as f32 * scale) as u32).max(1),
         )
     }
 
-    fn get_projection<L>(&self, layer: &L, scale: f32) -> Result<Projection>
+    /// Computes the projection of the layer at `index`, or fetches it from the cache
+    ///
+    /// `symbol_size` is the width and height of the symbol of the layer, which
+    /// the projection maps from. Layers moved by a `transform` are never cached, as
+    /// their corners usually change from one frame to the next.
+    fn get_projection<L>(
+        &self,
+        layer: &L,
+        index: usize,
+        transform: Option<&LayerTransform>,
+        symbol_size: (u32, u32),
+        scale: f32,
+        offset: (f32, f32),
+    ) -> Result<Projection>
     where
         L: SymbolArtLayer,
     {
-        let top_left = layer.top_left();
-        let bottom_left = layer.bottom_left();
-        let top_right = layer.top_right();
-        let bottom_right = layer.bottom_right();
-
-        let symbol_width = self.resource.symbol_pixels as f32;
-        let from = [
-            (0.0, 0.0),
-            (symbol_width, 0.0),
-            (symbol_width, symbol_width),
-            (0.0, symbol_width),
-        ];
-        let to = [
-            (top_left.x as f32 * scale, top_left.y as f32 * scale),
-            (top_right.x as f32 * scale, top_right.y as f32 * scale),
-            (bottom_right.x as f32 * scale, bottom_right.y as f32 * scale),
-            (bottom_left.x as f32 * scale, bottom_left.y as f32 * scale),
-        ];
+        let corners = layer.corners();
+        let solve = || {
+            let points = Self::layer_points(corners, transform);
+            Self::solve_projection(points, symbol_size, scale, offset).map_err(|(from, to)| {
+                SARError::ProjectionError(index, layer.symbol().id(), from, to)
+            })
+        };
 
-        let projection =
-            imageproc::geometric_transformations::Projection::from_control_points(from, to)
-                .ok_or(SARError::ProjectionError(from, to))?;
+        match &self.projection_cache {
+            Some(cache) if transform.is_none() => cache.get_or_insert_with(
+                ProjectionKey::new(corners.map(|p| (p.x, p.y)), symbol_size, scale, offset),
+                solve,
+            ),
+            _ => solve(),
+        }
+    }
 
-        Ok(projection)
+    /// The corners of a layer in SymbolArt coordinates, moved by `transform`
+    fn layer_points(corners: [Position; 4], transform: Option<&LayerTransform>) -> [(f32, f32); 4] {
+        match transform {
+            Some(transform) => transform.apply(corners),
+            None => corners.map(|p| (p.x as f32, p.y as f32)),
+        }
     }
 
-    fn render_symbol(base: &mut RgbaImage, symbol: &mut RgbaImage, color: RenderColor) {
-        for (x, y, pixel) in base.enumerate_pixels_mut() {
-            let symbol_pixel = symbol.get_pixel(x, y);
-            if symbol_pixel[3] > 0 {
-                match color {
-                    RenderColor::Color(color) => pixel.blend(&color.into()),
-                    RenderColor::None => {
-                        pixel.blend(symbol_pixel);
-                    }
-                }
+    /// Computes the projection mapping the symbol onto the corners of the layer
+    ///
+    /// The whole symbol is mapped, so one that isn't square is stretched along
+    /// its shorter side. Fails with the control points when they are degenerate.
+    fn solve_projection(
+        corners: [(f32, f32); 4],
+        symbol_size: (u32, u32),
+        scale: f32,
+        offset: (f32, f32),
+    ) -> std::result::Result<Projection, ControlPoints> {
+        let (width, height) = (symbol_size.0 as f32, symbol_size.1 as f32);
+        let from = [(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)];
+        let to = corners.map(|(x, y)| (x * scale + offset.0, y * scale + offset.1));
+
+        imageproc::geometric_transformations::Projection::from_control_points(from, to)
+            .ok_or((from, to))
+    }
+
+    /// Draws only the layers whose index falls in `range`
+    ///
+    /// Layers keep the stacking order they have in the full composition, which
+    /// makes it possible to dump the SymbolArt being built up layer by layer.
+    /// The range is clamped to the number of layers.
+    pub fn draw_layers<S, L>(&self, sa: &S, range: Range<usize>) -> Result<RgbaImage>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let layers = sa.layers();
+        self.check_layer_count(layers.len())?;
+        let end = range.end.min(layers.len());
+        let start = range.start.min(end);
+        self.render(sa, &layers[start..end], start, 1.0)
+    }
+
+    /// Draws the SymbolArt scaled to fit in a `width` x `height` image, centered
+    ///
+    /// The scale is the largest that fits the view and the padding in the image
+    /// while preserving the aspect ratio, capped by `with_max_fit_scale` so that
+    /// tiny arts aren't blown up. The space left around the art is filled with the
+    /// background color, which is all there is for an art without a size. Both
+    /// dimensions must leave room for the padding, otherwise drawing fails with
+    /// `SARError::InvalidCanvasSize`.
+    pub fn draw_fit<S, L>(&self, sa: &S, width: u32, height: u32) -> Result<RgbaImage>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let padding = self.padding * 2;
+        if width <= padding || height <= padding {
+            return Err(SARError::InvalidCanvasSize(width, height));
+        }
+
+        // The checkerboard goes behind the space around the art as well. The
+        // output is opaque then, so premultiplying it changes nothing.
+        if let Some(cell) = self.checkerboard {
+            let drawer = Self {
+                checkerboard: None,
+                quantize: None,
+                premultiplied_alpha: false,
+                ..self.clone()
+            };
+            let mut fitted = drawer.draw_fit(sa, width, height)?;
+            effect::checkerboard(&mut fitted, cell, (0, 0));
+            if let Some(max_colors) = self.quantize {
+                effect::quantize(&mut fitted, max_colors);
             }
+            return Ok(fitted);
+        }
+
+        let mut background = self.background;
+        if self.premultiplied_alpha {
+            effect::premultiply(&mut background);
+        }
+        let mut fitted = RgbaImage::from_pixel(width, height, background);
+        if sa.width() == 0 || sa.height() == 0 {
+            return Ok(fitted);
         }
+
+        let scale = ((width - padding) as f32 / sa.width() as f32)
+            .min((height - padding) as f32 / sa.height() as f32)
+            .min(self.max_fit_scale);
+        let image = self.render(sa, &sa.layers(), 0, scale)?;
+        imageops::replace(
+            &mut fitted,
+            &image,
+            (width as i64 - image.width() as i64) / 2,
+            (height as i64 - image.height() as i64) / 2,
+        );
+        Ok(fitted)
     }
-}
 
-enum RenderColor {
-    Color(Color),
-    None,
-}
+    /// Tiles the SymbolArts into a grid of `cols` columns, like a contact sheet
+    ///
+    /// Each art is drawn with `draw_fit` into a `thumbnail` sized cell, and the
+    /// cells are separated by `gutter` transparent pixels. The grid has as many
+    /// rows as needed, and the cells after the last art are left transparent.
+    /// Fails with `SARError::InvalidCanvasSize` when `cols` is zero.
+    pub fn montage<S, L>(
+        &self,
+        arts: &[S],
+        cols: u32,
+        thumbnail: (u32, u32),
+        gutter: u32,
+    ) -> Result<RgbaImage>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let (width, height) = thumbnail;
+        if cols == 0 {
+            return Err(SARError::InvalidCanvasSize(0, height));
+        }
 
-impl Default for SymbolArtDrawer {
-    fn default() -> Self {
-        Self {
-            resource: resource::Resource::new().unwrap(),
-            canvas_size: (256, 256),
-            chunk_size: 10,
-            suppress_failure: true,
+        let rows = (arts.len() as u32).div_ceil(cols);
+        let extent = |cells: u32, size: u32| cells * size + cells.saturating_sub(1) * gutter;
+        let mut sheet = RgbaImage::new(extent(cols, width), extent(rows, height));
+        for (i, sa) in arts.iter().enumerate() {
+            let (col, row) = (i as u32 % cols, i as u32 / cols);
+            let image = self.draw_fit(sa, width, height)?;
+            imageops::replace(
+                &mut sheet,
+                &image,
+                (col * (width + gutter)) as i64,
+                (row * (height + gutter)) as i64,
+            );
         }
+        Ok(sheet)
     }
-}
 
-impl<S, L> Drawer<S, L> for SymbolArtDrawer
-where
-    S: SymbolArt<Layer = L>,
-    L: SymbolArtLayer + Sync,
-{
-    fn draw(&self, sa: &S) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
-        self.draw_with_scale(sa, 1.0)
+    /// Draws the SymbolArt once per scale, like `draw_with_scale` would
+    ///
+    /// The layers are read once for the whole chain. With the projection cache,
+    /// rendering the chain again, or a chain repeating a scale, reuses the
+    /// projections computed for those scales.
+    pub fn render_mipmaps<S, L>(&self, sa: &S, scales: &[f32]) -> Result<Vec<RgbaImage>>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let layers = sa.layers();
+        scales
+            .iter()
+            .map(|&scale| self.render(sa, &layers, 0, scale))
+            .collect()
     }
 
-    fn draw_with_scale(&self, sa: &S, scale: f32) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
-        let canvas_size = self.calc_canvas_size(scale);
-        let mut canvas = RgbaImage::from_pixel(canvas_size.0, canvas_size.1, image::Rgba([0; 4]));
+    /// Draws each SymbolArt like `draw_with_scale`, several at once
+    ///
+    /// The results are in the order of `arts`, so one art failing doesn't stop
+    /// the others. The arts are spread across the rayon threads, and the chunks
+    /// of each art are drawn on the same threads rather than new ones, so the
+    /// pool isn't oversubscribed however many arts there are. With
+    /// `with_single_threaded` the arts are drawn one after another instead.
+    pub fn draw_all<S, L>(&self, arts: &[S], scale: f32) -> Vec<Result<RgbaImage>>
+    where
+        S: SymbolArt<Layer = L> + Sync,
+        L: SymbolArtLayer + Sync,
+    {
+        let draw = |sa: &S| self.render(sa, &sa.layers(), 0, scale);
+        if self.single_threaded {
+            arts.iter().map(draw).collect()
+        } else {
+            arts.par_iter().map(draw).collect()
+        }
+    }
 
-        let (tx, rx) = mpsc::channel();
-        let mut overlays = sa
-            .layers()
-            .par_chunks(self.chunk_size)
-            .rev()
-            .enumerate()
-            .filter_map(|(i, chunk)| {
-                let tx = tx.clone();
-                let mut canvas = RgbaImage::new(canvas_size.0, canvas_size.1);
-                for layer in chunk.iter().rev() {
-                    if layer.is_hidden() {
-                        continue;
-                    }
+    /// Draws the SymbolArt like `draw`, returning every error instead of the first
+    ///
+    /// Layers that fail are left out of the image, as with `with_raise_error(false)`,
+    /// and their errors are returned in layer order. This makes it possible to
+    /// report every bad layer of a SymbolArt in one pass. The layers are always
+    /// drawn on the CPU. If the canvas itself can't be created, the image is
+    /// empty and the error is the only one returned.
+    pub fn draw_collect_errors<S, L>(&self, sa: &S) -> (RgbaImage, Vec<SARError>)
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let scale = 1.0;
+        let sampled = self.sampled_scale(scale);
+        let layers = sa.layers();
+        let canvas = self
+            .check_layer_count(layers.len())
+            .and_then(|_| self.new_canvas(sa, sampled));
+        let mut canvas = match canvas {
+            Ok(canvas) => canvas,
+            Err(e) => return (RgbaImage::new(0, 0), vec![e]),
+        };
 
-                    let image = match self.resource.get_image(layer.symbol().id()) {
-                        Some(image) => image,
-                        None => {
-                            if self.suppress_failure {
-                                continue;
-                            }
+        let errors = Mutex::new(Vec::new());
+        let overrides = HashMap::new();
+        let (overlays, _) = self.draw_chunks(
+            &layers,
+            0,
+            &overrides,
+            Viewport::centered(canvas.dimensions(), sampled),
+            None,
+            |i, e| {
+                errors
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push((i, e));
+                ControlFlow::Continue(())
+            },
+        );
 
-                            tx.send(SARError::SymbolNotFound(layer.symbol().id()))
-                                .unwrap();
-                            return None;
-                        }
-                    };
-
-                    let mut symbol = RgbaImage::new(canvas_size.0, canvas_size.1);
-                    let projection = match self.get_projection(layer, scale) {
-                        Ok(projection) => projection,
-                        Err(e) => {
-                            if self.suppress_failure {
-                                continue;
-                            }
-
-                            tx.send(e).unwrap();
-                            return None;
-                        }
-                    };
-
-                    imageproc::geometric_transformations::warp_into(
-                        &image.inner().to_image(),
-                        &projection,
-                        imageproc::geometric_transformations::Interpolation::Nearest,
-                        image::Rgba([0; 4]),
-                        &mut symbol,
-                    );
-
-                    if let resource::Image::Color(_) = image {
-                        SymbolArtDrawer::render_symbol(&mut canvas, &mut symbol, RenderColor::None);
-                    } else {
-                        SymbolArtDrawer::render_symbol(
-                            &mut canvas,
-                            &mut symbol,
-                            RenderColor::Color(layer.color()),
-                        );
-                    }
-                }
+        canvas
+            .pixels_mut()
+            .for_each(|pixel| *pixel = self.background);
+        self.overlay_all(&mut canvas, &overlays);
 
-                Some((i, canvas))
-            })
-            .collect::<Vec<_>>();
+        let mut errors = errors.into_inner().unwrap_or_else(|e| e.into_inner());
+        errors.sort_by_key(|(i, _)| *i);
+        let errors = errors.into_iter().map(|(_, e)| e).collect();
+        (self.finish(sa, scale, &canvas), errors)
+    }
 
-        drop(tx);
-        if let Ok(e) = rx.recv() {
-            return Err(e);
+    /// Draws the SymbolArt like `draw_with_scale` and encodes the image as PNG
+    ///
+    /// Use `draw_with_scale` to encode the image with other settings or formats.
+    pub fn draw_png<S, L>(&self, sa: &S, scale: f32) -> Result<Vec<u8>>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let image = self.draw_with_scale(sa, scale)?;
+
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png).write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            image::ExtendedColorType::Rgba8,
+        )?;
+        Ok(png)
+    }
+
+    /// Draws the SymbolArt like `draw_png`, storing `dpi` as its physical resolution
+    ///
+    /// The resolution is written to the `pHYs` chunk in pixels per meter, which
+    /// image editors read back as the DPI to import the image at. PNGs from
+    /// `draw_png` have no such chunk, so they are usually imported at 72 DPI.
+    pub fn draw_png_with_dpi<S, L>(&self, sa: &S, dpi: u32, scale: f32) -> Result<Vec<u8>>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let image = self.draw_with_scale(sa, scale)?;
+        let pixels_per_meter = (dpi as f64 / METERS_PER_INCH).round() as u32;
+
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, image.width(), image.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_pixel_dims(Some(png::PixelDimensions {
+            xppu: pixels_per_meter,
+            yppu: pixels_per_meter,
+            unit: png::Unit::Meter,
+        }));
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(image.as_raw()))
+            .map_err(|e| encoding_error(image::ImageFormat::Png, e))?;
+        Ok(png)
+    }
+
+    /// Draws the SymbolArt like `draw_with_scale` and encodes the image as WebP
+    ///
+    /// The image is encoded losslessly when `quality` is `None`, and lossy with
+    /// a `quality` from 0 to 100 otherwise. The alpha channel is kept either way.
+    /// A `quality` outside that range fails with `SARError::ImageError`, like
+    /// any other encoding failure.
+    #[cfg(feature = "webp")]
+    pub fn draw_webp<S, L>(&self, sa: &S, quality: Option<f32>, scale: f32) -> Result<Vec<u8>>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let image = self.draw_with_scale(sa, scale)?;
+
+        let encoder = webp::Encoder::from_rgba(image.as_raw(), image.width(), image.height());
+        let webp = match quality {
+            None => encoder.encode_simple(true, 75.0),
+            Some(quality) => encoder.encode_simple(false, quality),
         }
+        .map_err(|e| encoding_error(image::ImageFormat::WebP, format!("{e:?}")))?;
+        Ok(webp.to_vec())
+    }
 
-        overlays.sort_by_key(|(i, _)| *i);
-        for (_, overlay) in overlays {
-            imageops::overlay(&mut canvas, &overlay, 0, 0);
+    /// Draws the SymbolArt like `draw_with_scale`, returning its pixels as bytes in `order`
+    ///
+    /// The bytes are followed by the width and height of the image. The channels
+    /// are swapped in place, so no other buffer is allocated for `Bgra`.
+    pub fn draw_raw<S, L>(
+        &self,
+        sa: &S,
+        order: ChannelOrder,
+        scale: f32,
+    ) -> Result<(Vec<u8>, u32, u32)>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let image = self.draw_with_scale(sa, scale)?;
+        let (width, height) = image.dimensions();
+        let mut bytes = image.into_raw();
+        if order == ChannelOrder::Bgra {
+            bytes.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2));
         }
+        Ok((bytes, width, height))
+    }
 
-        let view_size = Self::calc_view_size(sa, scale);
-        Ok(canvas
-            .sub_image(
-                canvas_size.0 / 2 - view_size.0 / 2,
-                canvas_size.1 / 2 - view_size.1 / 2,
-                view_size.0,
-                view_size.1,
-            )
-            .to_image())
+    /// Draws the SymbolArt like `draw_with_scale` onto an opaque `background`, without alpha
+    ///
+    /// The image is composited over `background`, so transparent pixels take
+    /// its color and semi-transparent ones are blended with it. This suits
+    /// formats like JPEG, which would otherwise drop the alpha of each pixel
+    /// and leave its color as is. The background of the drawer, if any, lies
+    /// between the layers and `background`.
+    pub fn draw_rgb<S, L>(&self, sa: &S, background: Rgb<u8>, scale: f32) -> Result<RgbImage>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let image = self.draw_with_scale(sa, scale)?;
+        Ok(RgbImage::from_fn(image.width(), image.height(), |x, y| {
+            let source = image.get_pixel(x, y);
+            if self.premultiplied_alpha {
+                let transparency = 255 - source[3] as u16;
+                Rgb(std::array::from_fn(|channel| {
+                    let beneath = (background[channel] as u16 * transparency / 255) as u8;
+                    source[channel].saturating_add(beneath)
+                }))
+            } else {
+                let mut pixel = background.to_rgba();
+                pixel.blend(source);
+                pixel.to_rgb()
+            }
+        }))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use image::codecs::png::PngEncoder;
+    /// Draws the SymbolArt like `draw_with_scale`, reusing the image of an identical art
+    ///
+    /// The images are cached by the content of the layers, the view size and the
+    /// scale, so an art parsed again or a copy of it comes from the cache. Errors
+    /// are never cached. See `with_cache_capacity` to size the cache.
+    pub fn draw_cached<S, L>(&self, sa: &S, scale: f32) -> Result<Arc<RgbaImage>>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        self.image_cache
+            .get_or_insert_with(ImageKey::new(sa, scale), || {
+                self.render(sa, &sa.layers(), 0, scale)
+            })
+    }
 
-    use super::*;
-    use crate::{parse, test::RAW_FILE};
+    /// Draws the SymbolArt like `draw_with_scale`, counting the layers left out
+    ///
+    /// With `with_raise_error(true)` the first failure is returned as with
+    /// `draw_with_scale`, so the report only counts hidden layers. The layers
+    /// are always drawn on the CPU.
+    pub fn draw_with_report<S, L>(&self, sa: &S, scale: f32) -> Result<(RgbaImage, RenderReport)>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let layers = sa.layers();
+        self.check_layer_count(layers.len())?;
+        let sampled = self.sampled_scale(scale);
+        let mut canvas = self.new_canvas(sa, sampled)?;
 
-    #[test]
-    fn test_drawer() {
-        let bytes = Vec::from(RAW_FILE);
-        let sa = parse(bytes).unwrap();
+        let report = Mutex::new(RenderReport {
+            hidden: layers.iter().filter(|layer| !self.is_drawn(*layer)).count(),
+            off_canvas: self.off_canvas_layers(sa, scale)?.len(),
+            ..Default::default()
+        });
+        let overrides = HashMap::new();
+        let viewport = Viewport::centered(canvas.dimensions(), sampled);
+        let overlays = self.draw_overlays_with(&layers, 0, &overrides, viewport, None, |e| {
+            report.lock().unwrap_or_else(|e| e.into_inner()).record(e)
+        })?;
 
-        let drawer = SymbolArtDrawer::new().with_raise_error(true);
-        let image = drawer.draw(&sa).unwrap();
+        canvas
+            .pixels_mut()
+            .for_each(|pixel| *pixel = self.background);
+        self.overlay_all(&mut canvas, &overlays);
 
-        // Assert
-        let mut buff = Vec::new();
-        image
-            .write_with_encoder(PngEncoder::new(&mut buff))
-            .unwrap();
-        assert_eq!(buff.len(), include_bytes!("fixture/test.png").len());
+        let report = report.into_inner().unwrap_or_else(|e| e.into_inner());
+        Ok((self.finish(sa, scale, &canvas), report))
     }
 
-    #[test]
-    fn test_drawer_with_scale() {
-        let bytes = Vec::from(RAW_FILE);
-        let sa = parse(bytes).unwrap();
+    /// Draws the SymbolArt like `draw_with_scale`, giving up once `cancel` is set
+    ///
+    /// Every chunk checks the flag before each of its layers, so setting it from
+    /// another thread stops the render within a layer and returns
+    /// `SARError::Cancelled`, which makes it possible to enforce a deadline. A
+    /// render that is done before the flag is set returns its image. The layers
+    /// are always drawn on the CPU.
+    pub fn draw_with_cancel<S, L>(
+        &self,
+        sa: &S,
+        scale: f32,
+        cancel: &AtomicBool,
+    ) -> Result<RgbaImage>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(SARError::Cancelled);
+        }
+        let layers = sa.layers();
+        self.check_layer_count(layers.len())?;
+        let sampled = self.sampled_scale(scale);
+        let mut canvas = self.new_canvas(sa, sampled)?;
 
-        let drawer = SymbolArtDrawer::default();
-        let image = drawer.draw_with_scale(&sa, 2.0).unwrap();
+        let viewport = Viewport::centered(canvas.dimensions(), sampled);
+        let overlays =
+            self.draw_overlays_with(&layers, 0, &HashMap::new(), viewport, Some(cancel), |_| {})?;
 
-        // Assert
-        let mut buff = Vec::new();
-        image
-            .write_with_encoder(PngEncoder::new(&mut buff))
-            .unwrap();
-        assert_eq!(buff.len(), include_bytes!("fixture/testx2.png").len());
+        canvas
+            .pixels_mut()
+            .for_each(|pixel| *pixel = self.background);
+        self.overlay_all(&mut canvas, &overlays);
+
+        Ok(self.finish(sa, scale, &canvas))
+    }
+
+    /// Draws the SymbolArt like `draw_with_scale`, moving the layers in `overrides` first
+    ///
+    /// The keys are indices into `SymbolArt::layers`, and the layers that aren't
+    /// listed are drawn as they are. Keys past the last layer are ignored. The
+    /// SymbolArt itself is left untouched, so the same one can be drawn with a
+    /// different transform for every frame of an animation.
+    pub fn draw_with_overrides<S, L>(
+        &self,
+        sa: &S,
+        overrides: &HashMap<usize, LayerTransform>,
+        scale: f32,
+    ) -> Result<RgbaImage>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let layers = sa.layers();
+        self.check_layer_count(layers.len())?;
+        let sampled = self.sampled_scale(scale);
+        let mut canvas = self.new_canvas(sa, sampled)?;
+        self.compose(&layers, 0, overrides, sampled, &mut canvas)?;
+
+        Ok(self.finish(sa, scale, &canvas))
+    }
+
+    /// Draws both SymbolArts like `draw_with_scale` and highlights where they differ
+    ///
+    /// Pixels that differ are magenta, and the identical ones are kept with a
+    /// quarter of their alpha. When the sizes differ, the images are aligned on
+    /// their top-left corner and the result is as large as both.
+    pub fn diff<S, L>(&self, a: &S, b: &S, scale: f32) -> Result<RgbaImage>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let a = self.draw_with_scale(a, scale)?;
+        let b = self.draw_with_scale(b, scale)?;
+        Ok(effect::diff(&a, &b))
+    }
+
+    /// The indices of the drawn layers that fall entirely outside of the output
+    ///
+    /// These layers are drawn without error but contribute no pixel, which
+    /// usually comes from a coordinate mistake. Like `content_bounds`, a layer
+    /// is located by its four projected corners, and is off-canvas when they
+    /// all lie past the same edge of the view. With `with_auto_crop` the whole
+    /// canvas is kept, so only the layers outside of it count.
+    pub fn off_canvas_layers<S, L>(&self, sa: &S, scale: f32) -> Result<Vec<usize>>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer,
+    {
+        let canvas_size = self.calc_canvas_dimensions(sa, scale)?;
+        let (left, top, right, bottom) = if self.auto_crop {
+            (0.0, 0.0, canvas_size.0 as f32, canvas_size.1 as f32)
+        } else {
+            let view_size = Self::calc_view_size(sa, scale);
+            let left = (canvas_size.0 / 2 - view_size.0 / 2) as f32;
+            let top = (canvas_size.1 / 2 - view_size.1 / 2) as f32;
+            (
+                left,
+                top,
+                left + view_size.0 as f32,
+                top + view_size.1 as f32,
+            )
+        };
+
+        Ok(self
+            .projected_layer_bounds(sa, scale, canvas_size)
+            .into_iter()
+            .filter(|(_, (min_x, min_y, max_x, max_y))| {
+                *max_x <= left || *min_x >= right || *max_y <= top || *min_y >= bottom
+            })
+            .map(|(index, _)| index)
+            .collect())
+    }
+
+    /// The `(min_x, min_y, max_x, max_y)` of the projected corners of each drawn layer, on the canvas
+    fn projected_layer_bounds<S, L>(
+        &self,
+        sa: &S,
+        scale: f32,
+        canvas_size: (u32, u32),
+    ) -> Vec<(usize, (f32, f32, f32, f32))>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer,
+    {
+        let offset = Self::calc_canvas_offset(canvas_size, scale);
+        sa.layers()
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| self.is_drawn(*layer))
+            .map(|(index, layer)| {
+                let bounds = layer.corners().iter().fold(
+                    (
+                        f32::INFINITY,
+                        f32::INFINITY,
+                        f32::NEG_INFINITY,
+                        f32::NEG_INFINITY,
+                    ),
+                    |(min_x, min_y, max_x, max_y), p| {
+                        let x = p.x as f32 * scale + offset.0;
+                        let y = p.y as f32 * scale + offset.1;
+                        (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+                    },
+                );
+                (index, bounds)
+            })
+            .collect()
+    }
+
+    /// Draws the part of the output of `draw_with_scale` that `tile` covers
+    ///
+    /// Only a buffer the size of the tile is allocated and the layers outside of
+    /// it are skipped, so an art can be exported at a scale too large for one
+    /// image by drawing it tile by tile. With `Interpolation::Nearest` the tiles
+    /// stitch into the same image, other interpolations may round a channel the
+    /// other way here and there. The tile is clipped to the output, failing with
+    /// `SARError::InvalidCanvasSize` if nothing is left of it.
+    ///
+    /// `with_auto_crop`, `with_padding`, `with_flip`, `with_supersample` and
+    /// `with_quantize` are ignored, as they depend on the whole image.
+    pub fn draw_tile<S, L>(&self, sa: &S, scale: f32, tile: Rect) -> Result<RgbaImage>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let layers = sa.layers();
+        self.check_layer_count(layers.len())?;
+        let canvas_size = self.calc_canvas_dimensions(sa, scale)?;
+        let view_size = Self::calc_view_size(sa, scale);
+        let tile = (view_size.0 > 0 && view_size.1 > 0)
+            .then(|| Rect::at(0, 0).of_size(view_size.0, view_size.1))
+            .and_then(|view| view.intersect(tile))
+            .ok_or(SARError::InvalidCanvasSize(tile.width(), tile.height()))?;
+
+        // Shift the SymbolArt so the top-left corner of the tile is at the origin, see `crop_view`
+        let centered = Viewport::centered(canvas_size, scale).offset;
+        let offset = (
+            centered.0 - (canvas_size.0 / 2 - view_size.0 / 2) as f32 - tile.left() as f32,
+            centered.1 - (canvas_size.1 / 2 - view_size.1 / 2) as f32 - tile.top() as f32,
+        );
+        let mut image = RgbaImage::new(tile.width(), tile.height());
+        self.compose_at(&layers, 0, &HashMap::new(), scale, offset, &mut image)?;
+        self.apply_color_passes(&mut image, (tile.left() as u32, tile.top() as u32));
+        self.apply_premultiplied_alpha(&mut image);
+
+        Ok(image)
+    }
+
+    /// Draws a single layer in isolation on a transparent canvas
+    ///
+    /// The layer goes through the same projection, warp and coloring as in a full
+    /// composition, and is drawn even if it is hidden. Unlike a full composition,
+    /// a missing symbol or a failed projection is always reported as an error.
+    pub fn draw_single_layer<S, L>(&self, sa: &S, index: usize) -> Result<RgbaImage>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        self.draw_single_layer_with_scale(sa, index, 1.0)
+    }
+
+    /// Draws a single layer like `draw_single_layer`, at the given scale
+    pub fn draw_single_layer_with_scale<S, L>(
+        &self,
+        sa: &S,
+        index: usize,
+        scale: f32,
+    ) -> Result<RgbaImage>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let layers = sa.layers();
+        self.check_layer_count(layers.len())?;
+        let layer = layers
+            .get(index)
+            .ok_or(SARError::LayerOutOfRange(index, layers.len()))?;
+
+        let sampled = self.sampled_scale(scale);
+        let mut canvas = self.new_canvas(sa, sampled)?;
+        let offset = Self::calc_canvas_offset(canvas.dimensions(), sampled);
+        self.draw_layer(layer, index, None, sampled, offset, &mut canvas)?;
+
+        Ok(self.finish(sa, scale, &canvas))
+    }
+
+    /// Renders the given layers of the SymbolArt, cropped to its view size
+    ///
+    /// `first` is the index of the first of the layers in the SymbolArt, which
+    /// errors report the layers by.
+    fn render<S, L>(&self, sa: &S, layers: &[L], first: usize, scale: f32) -> Result<RgbaImage>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("draw", layers = layers.len(), scale).entered();
+
+        self.check_layer_count(layers.len())?;
+        let sampled = self.sampled_scale(scale);
+        let mut canvas = self.new_canvas(sa, sampled)?;
+        self.compose(layers, first, &HashMap::new(), sampled, &mut canvas)?;
+
+        Ok(self.finish(sa, scale, &canvas))
+    }
+
+    /// Allocates a transparent canvas that is large enough to hold the view
+    fn new_canvas<S>(&self, sa: &S, scale: f32) -> Result<RgbaImage>
+    where
+        S: SymbolArt,
+    {
+        let (width, height) = self.calc_canvas_dimensions(sa, scale)?;
+        Ok(RgbaImage::new(width, height))
+    }
+
+    /// Fails if there are more layers than `with_max_layers` allows
+    fn check_layer_count(&self, count: usize) -> Result<()> {
+        if count > self.max_layers {
+            return Err(SARError::TooManyLayers(count, self.max_layers));
+        }
+        Ok(())
+    }
+
+    /// The dimensions of the canvas allocated by `new_canvas`
+    fn calc_canvas_dimensions<S>(&self, sa: &S, scale: f32) -> Result<(u32, u32)>
+    where
+        S: SymbolArt,
+    {
+        let view_size = Self::calc_view_size(sa, scale);
+        let canvas_size = self.calc_canvas_size(scale)?;
+        // Grow the canvas when the view doesn't fit, so the centered crop stays in bounds
+        Ok((
+            canvas_size.0.max(view_size.0),
+            canvas_size.1.max(view_size.1),
+        ))
+    }
+
+    /// The rectangle the visible layers cover in the output image, without drawing them
+    ///
+    /// Each layer contributes its four projected corners, so the bounds cover the
+    /// whole quad of a layer even where its symbol leaves pixels transparent.
+    /// The rectangle is given for the view sized output, after the padding and
+    /// flips, and may extend past the image when layers are outside of the view.
+    /// `with_auto_crop` is not taken into account. Returns `None` when every
+    /// layer is hidden.
+    pub fn content_bounds<S, L>(&self, sa: &S, scale: f32) -> Result<Option<Rect>>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer,
+    {
+        let canvas_size = self.calc_canvas_dimensions(sa, scale)?;
+        let view_size = Self::calc_view_size(sa, scale);
+        // Where the top-left corner of the output lies on the canvas, see `crop_view`
+        let origin = (
+            (canvas_size.0 / 2 - view_size.0 / 2) as f32 - self.padding as f32,
+            (canvas_size.1 / 2 - view_size.1 / 2) as f32 - self.padding as f32,
+        );
+
+        let Some((min_x, min_y, max_x, max_y)) = self
+            .projected_layer_bounds(sa, scale, canvas_size)
+            .into_iter()
+            .map(|(_, bounds)| bounds)
+            .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+        else {
+            return Ok(None);
+        };
+        let (min_x, min_y) = (min_x - origin.0, min_y - origin.1);
+        let (max_x, max_y) = (max_x - origin.0, max_y - origin.1);
+
+        let (mut left, mut top) = (min_x.floor() as i32, min_y.floor() as i32);
+        let width = (max_x.ceil() as i32 - left).max(1);
+        let height = (max_y.ceil() as i32 - top).max(1);
+        let output_size = (
+            (view_size.0 + self.padding * 2) as i32,
+            (view_size.1 + self.padding * 2) as i32,
+        );
+        if self.flip.0 {
+            left = output_size.0 - left - width;
+        }
+        if self.flip.1 {
+            top = output_size.1 - top - height;
+        }
+
+        Ok(Some(
+            Rect::at(left, top).of_size(width as u32, height as u32),
+        ))
+    }
+
+    /// Crops the composited canvas down to the output image
+    ///
+    /// The canvas is drawn at `sampled_scale`, and downsampled to `scale` when
+    /// supersampling.
+    fn finish<S>(&self, sa: &S, scale: f32, canvas: &RgbaImage) -> RgbaImage
+    where
+        S: SymbolArt,
+    {
+        let mut image = if self.auto_crop {
+            self.crop_drawn(canvas)
+        } else {
+            Self::crop_view(sa, self.sampled_scale(scale), canvas)
+        };
+        if self.supersample > 1 {
+            let (width, height) = if self.auto_crop {
+                let factor = self.supersample;
+                (
+                    image.width().div_ceil(factor),
+                    image.height().div_ceil(factor),
+                )
+            } else {
+                Self::calc_view_size(sa, scale)
+            };
+            image = effect::downsample(&image, width, height);
+        }
+
+        if self.padding > 0 {
+            image = effect::pad(&image, self.padding, self.background);
+        }
+        if self.flip.0 {
+            imageops::flip_horizontal_in_place(&mut image);
+        }
+        if self.flip.1 {
+            imageops::flip_vertical_in_place(&mut image);
+        }
+        self.apply_color_passes(&mut image, (0, 0));
+        if let Some(max_colors) = self.quantize {
+            effect::quantize(&mut image, max_colors);
+        }
+        self.apply_premultiplied_alpha(&mut image);
+
+        image
+    }
+
+    /// Applies the passes of `finish` that change each pixel on its own
+    ///
+    /// `origin` is the position of the image in the output, which the cells of
+    /// the checkerboard are aligned to.
+    fn apply_color_passes(&self, image: &mut RgbaImage, origin: (u32, u32)) {
+        if self.grayscale {
+            effect::grayscale(image);
+        }
+        if self.tint.0[..3] != [255; 3] {
+            effect::tint(image, self.tint);
+        }
+        if let Some(cell) = self.checkerboard {
+            effect::checkerboard(image, cell, origin);
+        }
+    }
+
+    /// Premultiplies the output if `with_premultiplied_alpha` is set
+    ///
+    /// Must stay the last pass, every other one works in straight alpha.
+    fn apply_premultiplied_alpha(&self, image: &mut RgbaImage) {
+        if self.premultiplied_alpha {
+            image.pixels_mut().for_each(effect::premultiply);
+        }
+    }
+
+    /// The scale the layers are drawn at, `with_supersample` times the output scale
+    fn sampled_scale(&self, scale: f32) -> f32 {
+        scale * self.supersample as f32
+    }
+
+    /// Crops the canvas to the pixels that differ from the background
+    fn crop_drawn(&self, canvas: &RgbaImage) -> RgbaImage {
+        match effect::drawn_bounds(canvas, self.background) {
+            Some((x, y, width, height)) => canvas.view(x, y, width, height).to_image(),
+            None => RgbaImage::from_pixel(1, 1, self.background),
+        }
+    }
+
+    /// Crops the view of the SymbolArt out of the center of the canvas
+    fn crop_view<S>(sa: &S, scale: f32, canvas: &RgbaImage) -> RgbaImage
+    where
+        S: SymbolArt,
+    {
+        let (width, height) = canvas.dimensions();
+        let view_size = Self::calc_view_size(sa, scale);
+        canvas
+            .view(
+                width / 2 - view_size.0 / 2,
+                height / 2 - view_size.1 / 2,
+                view_size.0,
+                view_size.1,
+            )
+            .to_image()
+    }
+
+    /// Draws the SymbolArt chunk by chunk, returning the composite after each chunk
+    ///
+    /// Every frame contains the layers of all previous frames, which makes it
+    /// possible to animate the SymbolArt drawing itself. The number of frames is
+    /// `ceil(layer_count / chunk_size)`, so the chunk size controls the pace.
+    pub fn draw_staged<S, L>(&self, sa: &S, scale: f32) -> Result<Vec<RgbaImage>>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let layers = sa.layers();
+        self.check_layer_count(layers.len())?;
+        let sampled = self.sampled_scale(scale);
+        let mut canvas = self.new_canvas(sa, sampled)?;
+        let overlays = self.draw_overlays(&layers, 0, sampled, canvas.dimensions())?;
+
+        canvas
+            .pixels_mut()
+            .for_each(|pixel| *pixel = self.background);
+        let frames = overlays
+            .into_iter()
+            .map(|overlay| {
+                self.overlay_all(&mut canvas, std::slice::from_ref(&overlay));
+                self.finish(sa, scale, &canvas)
+            })
+            .collect();
+
+        Ok(frames)
+    }
+
+    /// Encodes the staged composites of the SymbolArt as a looping animated GIF
+    ///
+    /// Every chunk of layers adds a frame shown for `frame_delay_ms`, and the
+    /// finished SymbolArt is held for `ANIMATION_HOLD_MS` before the animation
+    /// restarts. Use `with_chunk_size` to trade the number of frames for speed.
+    pub fn draw_animation<S, L>(&self, sa: &S, frame_delay_ms: u16) -> Result<Vec<u8>>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer + Sync,
+    {
+        let images = self.draw_staged(sa, 1.0)?;
+        let last = match images.last() {
+            Some(last) => last.clone(),
+            None => self.render(sa, &[], 0, 1.0)?,
+        };
+
+        let delay = Delay::from_numer_denom_ms(frame_delay_ms as u32, 1);
+        let hold = Delay::from_numer_denom_ms(ANIMATION_HOLD_MS.max(frame_delay_ms as u32), 1);
+        let mut frames = images
+            .into_iter()
+            .map(|image| Frame::from_parts(image, 0, 0, delay))
+            .collect::<Vec<_>>();
+        frames.push(Frame::from_parts(last, 0, 0, hold));
+
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            encoder.set_repeat(Repeat::Infinite)?;
+            encoder.encode_frames(frames)?;
+        }
+
+        Ok(bytes)
+    }
+
+    /// Exports the SymbolArt as an SVG document with one `<image>` per visible layer
+    ///
+    /// Each layer embeds its colored symbol as a PNG and places it with the affine
+    /// transform mapping the symbol onto the top-left, top-right and bottom-left
+    /// corners of the layer. SVG has no perspective transform, so layers that
+    /// aren't parallelograms are approximated. The document uses the coordinates
+    /// of the SymbolArt and has its view size. The background is emitted as a
+    /// `<rect>`, while the post-processing options like padding or tint are not
+    /// applied.
+    pub fn to_svg<S, L>(&self, sa: &S) -> Result<String>
+    where
+        S: SymbolArt<Layer = L>,
+        L: SymbolArtLayer,
+    {
+        self.check_layer_count(sa.layers().len())?;
+        let (width, height) = Self::calc_view_size(sa, 1.0);
+        let origin = (
+            SA_CENTER - (width / 2) as f32,
+            SA_CENTER - (height / 2) as f32,
+        );
+
+        let mut svg = String::new();
+        write!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="{} {} {width} {height}""#,
+            origin.0, origin.1,
+        )
+        .unwrap();
+        if self.interpolation == Interpolation::Nearest {
+            svg.push_str(r#" style="image-rendering:pixelated""#);
+        }
+        svg.push_str(">\n");
+
+        let Rgba([r, g, b, a]) = self.background;
+        if a > 0 {
+            writeln!(
+                svg,
+                r#"  <rect x="{}" y="{}" width="{width}" height="{height}" fill="rgb({r},{g},{b})" fill-opacity="{}"/>"#,
+                origin.0,
+                origin.1,
+                a as f32 / 255.0,
+            )
+            .unwrap();
+        }
+
+        // Later elements are painted on top, so the layers go from the bottom
+        let layers = sa.layers();
+        for layer in self.bottom_to_top(layers.len()).map(|i| &layers[i]) {
+            if !self.is_drawn(layer) {
+                continue;
+            }
+
+            match self.layer_to_svg(layer) {
+                Ok(element) => svg.push_str(&element),
+                Err(_) if self.suppress_failure => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        Ok(svg)
+    }
+
+    /// Renders the colored symbol of the layer into a transformed SVG `<image>`
+    fn layer_to_svg<L>(&self, layer: &L) -> Result<String>
+    where
+        L: SymbolArtLayer,
+    {
+        let (image, is_colored) = self.symbol_image(layer.symbol().id())?;
+
+        let mut symbol = image.clone();
+        let mut colored = RgbaImage::new(symbol.width(), symbol.height());
+        let color = if is_colored {
+            RenderColor::None
+        } else {
+            RenderColor::Color(layer.color())
+        };
+        let antialias = self.interpolation != Interpolation::Nearest;
+        let alpha = self.layer_alpha(layer);
+        SymbolArtDrawer::render_symbol(
+            &mut colored,
+            &mut symbol,
+            color,
+            antialias,
+            alpha,
+            BlendMode::Normal,
+        );
+
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png).write_image(
+            colored.as_raw(),
+            colored.width(),
+            colored.height(),
+            image::ExtendedColorType::Rgba8,
+        )?;
+
+        let size = image.width() as f32;
+        let (top_left, top_right, bottom_left) =
+            (layer.top_left(), layer.top_right(), layer.bottom_left());
+        let matrix = [
+            (top_right.x as f32 - top_left.x as f32) / size,
+            (top_right.y as f32 - top_left.y as f32) / size,
+            (bottom_left.x as f32 - top_left.x as f32) / size,
+            (bottom_left.y as f32 - top_left.y as f32) / size,
+            top_left.x as f32,
+            top_left.y as f32,
+        ]
+        .map(|value| value.to_string())
+        .join(" ");
+
+        let style = match layer.blend_mode() {
+            BlendMode::Normal => "",
+            BlendMode::Multiply => r#" style="mix-blend-mode:multiply""#,
+            BlendMode::Screen => r#" style="mix-blend-mode:screen""#,
+            BlendMode::Additive => r#" style="mix-blend-mode:plus-lighter""#,
+        };
+        Ok(format!(
+            r#"  <image width="{size}" height="{size}" transform="matrix({matrix})"{style} href="data:image/png;base64,{}"/>
+"#,
+            BASE64.encode(png)
+        ))
+    }
+
+    /// Composites the visible layers onto the canvas
+    ///
+    /// The canvas is cleared with the background color first, and the SymbolArt
+    /// is centered on it regardless of its dimensions. The layers whose index is
+    /// in `overrides` are moved by their transform.
+    fn compose<L>(
+        &self,
+        layers: &[L],
+        first: usize,
+        overrides: &HashMap<usize, LayerTransform>,
+        scale: f32,
+        canvas: &mut RgbaImage,
+    ) -> Result<()>
+    where
+        L: SymbolArtLayer + Sync,
+    {
+        let viewport = Viewport::centered(canvas.dimensions(), scale);
+        self.compose_at(layers, first, overrides, scale, viewport.offset, canvas)
+    }
+
+    /// Composites the layers like `compose`, with the SymbolArt origin at `offset` on the canvas
+    fn compose_at<L>(
+        &self,
+        layers: &[L],
+        first: usize,
+        overrides: &HashMap<usize, LayerTransform>,
+        scale: f32,
+        offset: (f32, f32),
+        canvas: &mut RgbaImage,
+    ) -> Result<()>
+    where
+        L: SymbolArtLayer + Sync,
+    {
+        let viewport = Viewport {
+            size: canvas.dimensions(),
+            offset,
+            scale,
+        };
+        let overlays = match self.draw_gpu_overlay(layers, first, overrides, viewport)? {
+            Some(overlay) => vec![overlay],
+            None => self.draw_overlays_with(layers, first, overrides, viewport, None, |_| {})?,
+        };
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("composite", overlays = overlays.len()).entered();
+        canvas
+            .pixels_mut()
+            .for_each(|pixel| *pixel = self.background);
+        self.overlay_all(canvas, &overlays);
+
+        Ok(())
+    }
+
+    /// Blends the overlays onto the canvas in order, like `imageops::overlay` would
+    ///
+    /// Blending rounds to `u8` after every step and isn't associative, so the
+    /// overlays can't be merged pairwise without changing the result. The rows of
+    /// the canvas are blended in parallel instead, unless `with_single_threaded`
+    /// is set, each pixel going through the overlays from the bottom to the top.
+    fn overlay_all(&self, canvas: &mut RgbaImage, overlays: &[RgbaImage]) {
+        let row_len = canvas.width() as usize * 4;
+        if row_len == 0 {
+            return;
+        }
+
+        let blend_row = |(y, row): (usize, &mut [u8])| {
+            let range = y * row_len..(y + 1) * row_len;
+            for overlay in overlays {
+                let source = &overlay.as_raw()[range.clone()];
+                for (pixel, top) in row.chunks_exact_mut(4).zip(source.chunks_exact(4)) {
+                    Rgba::from_slice_mut(pixel).blend(Rgba::from_slice(top));
+                }
+            }
+        };
+
+        if self.single_threaded {
+            canvas.chunks_mut(row_len).enumerate().for_each(blend_row);
+        } else {
+            canvas
+                .par_chunks_mut(row_len)
+                .enumerate()
+                .for_each(blend_row);
+        }
+    }
+
+    /// Draws the layers in parallel chunks, one overlay per chunk
+    ///
+    /// The overlays are returned in compositing order, from the bottom to the top.
+    fn draw_overlays<L>(
+        &self,
+        layers: &[L],
+        first: usize,
+        scale: f32,
+        canvas_size: (u32, u32),
+    ) -> Result<Vec<RgbaImage>>
+    where
+        L: SymbolArtLayer + Sync,
+    {
+        let viewport = Viewport::centered(canvas_size, scale);
+        self.draw_overlays_with(layers, first, &HashMap::new(), viewport, None, |_| {})
+    }
+
+    /// Draws the overlays like `draw_overlays`, showing every failure to `observe`
+    ///
+    /// Fails with `SARError::Cancelled` if `cancel` stopped a chunk before its
+    /// last layer, see `draw_chunks`.
+    fn draw_overlays_with<L, F>(
+        &self,
+        layers: &[L],
+        first: usize,
+        overrides: &HashMap<usize, LayerTransform>,
+        viewport: Viewport,
+        cancel: Option<&AtomicBool>,
+        observe: F,
+    ) -> Result<Vec<RgbaImage>>
+    where
+        L: SymbolArtLayer + Sync,
+        F: Fn(&SARError) + Sync,
+    {
+        let (tx, rx) = mpsc::channel();
+        let (overlays, cancelled) =
+            self.draw_chunks(layers, first, overrides, viewport, cancel, |_, e| {
+                observe(&e);
+                if self.suppress_failure {
+                    return ControlFlow::Continue(());
+                }
+
+                tx.send(e).unwrap();
+                ControlFlow::Break(())
+            });
+
+        drop(tx);
+        if cancelled {
+            return Err(SARError::Cancelled);
+        }
+        if let Ok(e) = rx.recv() {
+            return Err(e);
+        }
+
+        Ok(overlays)
+    }
+
+    /// Draws the layers in parallel chunks, handing the failures to `on_failure`
+    ///
+    /// `on_failure` receives the index of the failing layer and decides whether
+    /// the rest of its chunk is drawn. Every chunk also stops before its next
+    /// layer once `cancel` is set. Chunks that are stopped are left out of the
+    /// returned overlays, which come with whether `cancel` stopped any chunk.
+    fn draw_chunks<L, F>(
+        &self,
+        layers: &[L],
+        first: usize,
+        overrides: &HashMap<usize, LayerTransform>,
+        viewport: Viewport,
+        cancel: Option<&AtomicBool>,
+        on_failure: F,
+    ) -> (Vec<RgbaImage>, bool)
+    where
+        L: SymbolArtLayer + Sync,
+        F: Fn(usize, SARError) -> ControlFlow<()> + Sync,
+    {
+        let Viewport {
+            size: canvas_size,
+            offset,
+            scale,
+        } = viewport;
+        // Overlays are composited with `Normal`, so other modes need every layer
+        // beneath them in the same overlay
+        let chunk_size = if self.uses_blend_modes(layers) {
+            layers.len().max(1)
+        } else {
+            match self.chunk_size {
+                ChunkSize::Fixed(size) => size,
+                ChunkSize::Auto if self.single_threaded => layers.len().max(1),
+                ChunkSize::Auto => layers
+                    .len()
+                    .div_ceil(rayon::current_num_threads())
+                    .max(MIN_AUTO_CHUNK_SIZE),
+            }
+        };
+
+        let chunk_count = layers.len().div_ceil(chunk_size);
+        // Rayon workers don't see the span of the calling thread, so the chunks
+        // name it as their parent
+        #[cfg(feature = "tracing")]
+        let parent = tracing::Span::current();
+        let cancelled = AtomicBool::new(false);
+        let draw_chunk = |(chunk_index, chunk): (usize, &[L])| {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!(parent: &parent, "chunk", index = chunk_index, layers = chunk.len()).entered();
+
+            let mut overlay = RgbaImage::new(canvas_size.0, canvas_size.1);
+            let chunk_first = first + chunk_index * chunk_size;
+            for j in self.bottom_to_top(chunk.len()) {
+                if cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+                    cancelled.store(true, Ordering::Relaxed);
+                    return None;
+                }
+                let layer = &chunk[j];
+                if !self.is_drawn(layer) {
+                    continue;
+                }
+
+                let index = chunk_first + j;
+                let transform = overrides.get(&index);
+                if let Err(e) =
+                    self.draw_layer(layer, index, transform, scale, offset, &mut overlay)
+                {
+                    on_failure(index, e).continue_value()?;
+                }
+            }
+
+            // The position of the overlay from the bottom
+            let i = match self.layer_order {
+                LayerOrder::TopFirst => chunk_count - 1 - chunk_index,
+                LayerOrder::BottomFirst => chunk_index,
+            };
+            Some((i, overlay))
+        };
+
+        let mut overlays = if self.single_threaded {
+            layers
+                .chunks(chunk_size)
+                .enumerate()
+                .filter_map(draw_chunk)
+                .collect::<Vec<_>>()
+        } else {
+            layers
+                .par_chunks(chunk_size)
+                .enumerate()
+                .filter_map(draw_chunk)
+                .collect::<Vec<_>>()
+        };
+
+        overlays.sort_by_key(|(i, _)| *i);
+        let overlays = overlays.into_iter().map(|(_, overlay)| overlay).collect();
+        (overlays, cancelled.into_inner())
+    }
+
+    /// Whether a drawn layer blends with a mode other than `BlendMode::Normal`
+    fn uses_blend_modes<L>(&self, layers: &[L]) -> bool
+    where
+        L: SymbolArtLayer,
+    {
+        layers
+            .iter()
+            .any(|layer| self.is_drawn(layer) && layer.blend_mode() != BlendMode::Normal)
+    }
+
+    /// The indices of `len` layers in the order they are composited, from the bottom
+    fn bottom_to_top(&self, len: usize) -> impl Iterator<Item = usize> {
+        let order = self.layer_order;
+        (0..len).map(move |k| match order {
+            LayerOrder::TopFirst => len - 1 - k,
+            LayerOrder::BottomFirst => k,
+        })
+    }
+
+    /// Whether the layer is drawn, hidden layers only being with `with_include_hidden`
+    fn is_drawn<L>(&self, layer: &L) -> bool
+    where
+        L: SymbolArtLayer,
+    {
+        !layer.is_hidden() || self.include_hidden
+    }
+
+    /// The alpha the layer is blended with, faded by `with_hidden_alpha` if hidden
+    fn layer_alpha<L>(&self, layer: &L) -> f32
+    where
+        L: SymbolArtLayer,
+    {
+        let alpha = layer.alpha().clamp(0.0, 1.0);
+        if layer.is_hidden() {
+            alpha * self.hidden_alpha
+        } else {
+            alpha
+        }
+    }
+
+    /// Draws all the layers into a single overlay on the GPU
+    ///
+    /// Returns `None` when the layers have to be drawn on the CPU instead.
+    #[cfg(feature = "gpu")]
+    fn draw_gpu_overlay<L>(
+        &self,
+        layers: &[L],
+        first: usize,
+        overrides: &HashMap<usize, LayerTransform>,
+        viewport: Viewport,
+    ) -> Result<Option<RgbaImage>>
+    where
+        L: SymbolArtLayer,
+    {
+        let Some(gpu) = &self.gpu else {
+            return Ok(None);
+        };
+        if self.interpolation != Interpolation::Nearest || self.uses_blend_modes(layers) {
+            return Ok(None);
+        }
+        // The overrides aren't uploaded with the sheets
+        if layers
+            .iter()
+            .any(|layer| self.symbol_overrides.contains_key(&layer.symbol().id()))
+        {
+            return Ok(None);
+        }
+
+        let Viewport { offset, scale, .. } = viewport;
+        let mut quads = Vec::with_capacity(layers.len());
+        for i in self.bottom_to_top(layers.len()) {
+            let layer = &layers[i];
+            if !self.is_drawn(layer) {
+                continue;
+            }
+
+            let index = first + i;
+            match self.layer_quad(layer, index, overrides.get(&index), scale, offset) {
+                Ok(quad) => quads.push(quad),
+                Err(_) if self.suppress_failure => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(gpu.draw(&quads, viewport.size))
+    }
+
+    #[cfg(not(feature = "gpu"))]
+    fn draw_gpu_overlay<L>(
+        &self,
+        _layers: &[L],
+        _first: usize,
+        _overrides: &HashMap<usize, LayerTransform>,
+        _viewport: Viewport,
+    ) -> Result<Option<RgbaImage>> {
+        Ok(None)
+    }
+
+    /// Describes the layer for the GPU, failing like `draw_layer` would
+    #[cfg(feature = "gpu")]
+    fn layer_quad<L>(
+        &self,
+        layer: &L,
+        index: usize,
+        transform: Option<&LayerTransform>,
+        scale: f32,
+        offset: (f32, f32),
+    ) -> Result<gpu::Quad>
+    where
+        L: SymbolArtLayer,
+    {
+        let image = self
+            .resource
+            .get_image(layer.symbol().id())
+            .ok_or(SARError::SymbolNotFound(layer.symbol().id()))?;
+        let pixels = image.pixels();
+        self.get_projection(layer, index, transform, (pixels, pixels), scale, offset)?;
+
+        let corners = Self::layer_points(layer.corners(), transform)
+            .map(|(x, y)| (x * scale + offset.0, y * scale + offset.1));
+        let alpha = self.layer_alpha(layer);
+
+        Ok(gpu::Quad::new(&image, corners, layer.color().into(), alpha))
+    }
+
+    /// Warps the symbol of the layer at `index` and blends it onto the canvas
+    fn draw_layer<L>(
+        &self,
+        layer: &L,
+        index: usize,
+        transform: Option<&LayerTransform>,
+        scale: f32,
+        offset: (f32, f32),
+        canvas: &mut RgbaImage,
+    ) -> Result<()>
+    where
+        L: SymbolArtLayer,
+    {
+        let (source, colored) = self.symbol_image(layer.symbol().id())?;
+        let projection = {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("projection", index).entered();
+            self.get_projection(layer, index, transform, source.dimensions(), scale, offset)?
+        };
+        let corners = Self::layer_points(layer.corners(), transform)
+            .map(|(x, y)| (x * scale + offset.0, y * scale + offset.1));
+        if Self::is_off_canvas(corners, canvas.dimensions()) {
+            return Ok(());
+        }
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("warp", index).entered();
+        // `warp_into` writes every pixel, so the buffer doesn't need to be cleared
+        let mut symbol = self.scratch.take(canvas.dimensions());
+        imageproc::geometric_transformations::warp_into(
+            source,
+            &projection,
+            self.interpolation,
+            image::Rgba([0; 4]),
+            &mut symbol,
+        );
+
+        let antialias = self.interpolation != Interpolation::Nearest;
+        let alpha = self.layer_alpha(layer);
+        if colored {
+            SymbolArtDrawer::render_symbol(
+                canvas,
+                &mut symbol,
+                RenderColor::None,
+                antialias,
+                alpha,
+                layer.blend_mode(),
+            );
+        } else {
+            SymbolArtDrawer::render_symbol(
+                canvas,
+                &mut symbol,
+                RenderColor::Color(layer.color()),
+                antialias,
+                alpha,
+                layer.blend_mode(),
+            );
+        }
+        self.scratch.put(symbol);
+
+        Ok(())
+    }
+
+    /// The image the symbol `id` is drawn with, and whether it keeps its own colors
+    ///
+    /// Overrides from `with_symbol_override` take precedence over the resource.
+    fn symbol_image(&self, id: SymbolId) -> Result<(&RgbaImage, bool)> {
+        if let Some(image) = self.symbol_overrides.get(&id) {
+            return Ok((image, resource::Resource::is_colored(id)));
+        }
+
+        let image = self
+            .resource
+            .get_rgba_image(id)
+            .ok_or(SARError::SymbolNotFound(id))?;
+        Ok((image, resource::Resource::is_colored(id)))
+    }
+
+    /// Whether a layer with the given corners on the canvas leaves it untouched
+    ///
+    /// Only convex layers are known to stay within their corners, the symbol of
+    /// a twisted one may be projected anywhere. Interpolation samples up to two
+    /// pixels around, which the margin accounts for.
+    fn is_off_canvas(corners: [(f32, f32); 4], canvas_size: (u32, u32)) -> bool {
+        const MARGIN: f32 = 2.0;
+
+        let turns: [f32; 4] = std::array::from_fn(|i| {
+            let (a, b, c) = (corners[i], corners[(i + 1) % 4], corners[(i + 2) % 4]);
+            (b.0 - a.0) * (c.1 - b.1) - (b.1 - a.1) * (c.0 - b.0)
+        });
+        let convex = turns.iter().all(|&turn| turn > 0.0) || turns.iter().all(|&turn| turn < 0.0);
+        if !convex {
+            return false;
+        }
+
+        let (width, height) = (canvas_size.0 as f32, canvas_size.1 as f32);
+        corners.iter().all(|p| p.0 < -MARGIN)
+            || corners.iter().all(|p| p.1 < -MARGIN)
+            || corners.iter().all(|p| p.0 > width + MARGIN)
+            || corners.iter().all(|p| p.1 > height + MARGIN)
+    }
+
+    /// Blends the warped symbol onto the base image
+    ///
+    /// With `antialias`, the alpha of the warped symbol is used as coverage for
+    /// the layer color, so the smooth edges produced by interpolation are kept.
+    /// Otherwise any covered pixel receives the layer color as is.
+    /// The alpha of the source pixel is then scaled by the layer `alpha`, and
+    /// the pixel is blended onto the base with `mode`.
+    fn render_symbol(
+        base: &mut RgbaImage,
+        symbol: &mut RgbaImage,
+        color: RenderColor,
+        antialias: bool,
+        alpha: f32,
+        mode: BlendMode,
+    ) {
+        for (x, y, pixel) in base.enumerate_pixels_mut() {
+            let symbol_pixel = symbol.get_pixel(x, y);
+            if symbol_pixel[3] > 0 {
+                let mut source = match color {
+                    RenderColor::Color(color) => {
+                        let mut color: Rgba<u8> = color.into();
+                        if antialias {
+                            color[3] = (color[3] as u16 * symbol_pixel[3] as u16 / 255) as u8;
+                        }
+                        color
+                    }
+                    RenderColor::None => *symbol_pixel,
+                };
+                source[3] = (source[3] as f32 * alpha).round() as u8;
+                effect::blend(pixel, &source, mode);
+            }
+        }
+    }
+}
+
+/// The size of the canvas the layers are drawn onto, and where and at which
+/// scale the SymbolArt lies on it
+#[derive(Clone, Copy)]
+struct Viewport {
+    size: (u32, u32),
+    offset: (f32, f32),
+    scale: f32,
+}
+
+impl Viewport {
+    /// The viewport with the SymbolArt centered on a canvas of `size`
+    fn centered(size: (u32, u32), scale: f32) -> Self {
+        Self {
+            size,
+            offset: SymbolArtDrawer::calc_canvas_offset(size, scale),
+            scale,
+        }
+    }
+}
+
+/// The points a projection maps from the symbol onto the canvas
+type ControlPoints = ([(f32, f32); 4], [(f32, f32); 4]);
+
+/// The fewest layers `with_chunk_size_auto` puts in a chunk
+const MIN_AUTO_CHUNK_SIZE: usize = 4;
+
+/// The largest factor `with_supersample` accepts
+const MAX_SUPERSAMPLE: u32 = 4;
+
+/// How many images `draw_cached` keeps unless `with_cache_capacity` is used
+const DEFAULT_IMAGE_CACHE_CAPACITY: usize = 32;
+
+/// The coordinate of the SymbolArt center on both axes
+const SA_CENTER: f32 = 128.0;
+
+/// How long the finished SymbolArt is shown at the end of an animation
+const ANIMATION_HOLD_MS: u32 = 2000;
+
+/// The length of an inch, which `draw_png_with_dpi` converts the DPI by
+const METERS_PER_INCH: f64 = 0.0254;
+
+/// How `draw_chunks` splits the layers, set by `with_chunk_size` and `with_chunk_size_auto`
+#[derive(Clone, Copy)]
+enum ChunkSize {
+    Fixed(usize),
+    Auto,
+}
+
+enum RenderColor {
+    Color(Color),
+    None,
+}
+
+/// Wraps a failure of an encoder other than `image`'s into `SARError::ImageError`
+fn encoding_error(format: image::ImageFormat, e: impl std::fmt::Display) -> SARError {
+    SARError::ImageError(image::ImageError::Encoding(
+        image::error::EncodingError::new(format.into(), e.to_string()),
+    ))
+}
+
+impl Default for SymbolArtDrawer {
+    fn default() -> Self {
+        Self::try_new().expect("failed to load the bundled symbol resources")
+    }
+}
+
+impl<S, L> Drawer<S, L> for SymbolArtDrawer
+where
+    S: SymbolArt<Layer = L>,
+    L: SymbolArtLayer + Sync,
+{
+    fn draw(&self, sa: &S) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+        self.draw_with_scale(sa, 1.0)
+    }
+
+    fn draw_with_scale(&self, sa: &S, scale: f32) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+        self.render(sa, &sa.layers(), 0, scale)
+    }
+
+    fn draw_into(&self, sa: &S, target: &mut RgbaImage) -> Result<()> {
+        if target.width() == 0 || target.height() == 0 {
+            return Err(SARError::InvalidCanvasSize(target.width(), target.height()));
+        }
+
+        let layers = sa.layers();
+        self.check_layer_count(layers.len())?;
+        self.compose(&layers, 0, &HashMap::new(), 1.0, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parse,
+        test::{MockLayer, MockSymbolArt, RAW_FILE},
+        testing::assert_images_close,
+    };
+
+    /// How far the renders may stray from the fixtures, as blending rounds to `u8`
+    /// and the rounding may change between versions of `image`
+    const FIXTURE_TOLERANCE: u8 = 1;
+
+    #[test]
+    fn test_drawer() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let drawer = SymbolArtDrawer::new().with_raise_error(true);
+        let image = drawer.draw(&sa).unwrap();
+
+        let expected = image::load_from_memory(include_bytes!("fixture/test.png")).unwrap();
+        assert_images_close(&image, &expected.to_rgba8(), FIXTURE_TOLERANCE);
+    }
+
+    #[test]
+    fn test_drawer_with_scale() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let drawer = SymbolArtDrawer::default();
+        let image = drawer.draw_with_scale(&sa, 2.0).unwrap();
+
+        let expected = image::load_from_memory(include_bytes!("fixture/testx2.png")).unwrap();
+        assert_images_close(&image, &expected.to_rgba8(), FIXTURE_TOLERANCE);
+    }
+
+    #[test]
+    fn test_drawer_draw_png() {
+        let sa = parse(Vec::from(RAW_FILE)).unwrap();
+
+        let drawer = SymbolArtDrawer::default();
+        let png = drawer.draw_png(&sa, 2.0).unwrap();
+
+        assert_eq!(image::guess_format(&png).unwrap(), image::ImageFormat::Png);
+        let decoded = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(decoded, drawer.draw_with_scale(&sa, 2.0).unwrap());
+    }
+
+    #[cfg(feature = "webp")]
+    #[test]
+    fn test_drawer_draw_webp() {
+        let sa = parse(Vec::from(RAW_FILE)).unwrap();
+        let drawer = SymbolArtDrawer::default();
+        let image = drawer.draw(&sa).unwrap();
+
+        // The colors of transparent pixels aren't kept
+        let lossless = drawer.draw_webp(&sa, None, 1.0).unwrap();
+        assert_eq!(
+            image::guess_format(&lossless).unwrap(),
+            image::ImageFormat::WebP
+        );
+        let decoded = image::load_from_memory(&lossless).unwrap().to_rgba8();
+        assert_eq!(decoded.dimensions(), image.dimensions());
+        for (decoded, drawn) in decoded.pixels().zip(image.pixels()) {
+            assert_eq!(decoded[3], drawn[3]);
+            if drawn[3] != 0 {
+                assert_eq!(decoded, drawn);
+            }
+        }
+
+        let lossy = drawer.draw_webp(&sa, Some(50.0), 1.0).unwrap();
+        assert_ne!(lossy, lossless);
+        let decoded = image::load_from_memory(&lossy).unwrap().to_rgba8();
+        assert_eq!(decoded.dimensions(), image.dimensions());
+        assert!(decoded.pixels().any(|pixel| pixel[3] == 0));
+        assert!(decoded.pixels().any(|pixel| pixel[3] == 255));
+
+        assert!(matches!(
+            drawer.draw_webp(&sa, Some(150.0), 1.0),
+            Err(SARError::ImageError(_))
+        ));
+    }
+
+    #[test]
+    fn test_drawer_draw_png_with_dpi() {
+        let sa = parse(Vec::from(RAW_FILE)).unwrap();
+        let drawer = SymbolArtDrawer::default();
+        let png = drawer.draw_png_with_dpi(&sa, 300, 2.0).unwrap();
+
+        let decoder = png::Decoder::new(std::io::Cursor::new(&png));
+        let reader = decoder.read_info().unwrap();
+        let dims = reader.info().pixel_dims.unwrap();
+        assert_eq!((dims.xppu, dims.yppu), (11811, 11811));
+        assert_eq!(dims.unit, png::Unit::Meter);
+
+        let decoded = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(decoded, drawer.draw_with_scale(&sa, 2.0).unwrap());
+    }
+
+    #[test]
+    fn test_drawer_draw_raw() {
+        let sa = MockSymbolArt::with_layers(vec![MockLayer::new(
+            (100, 100),
+            (150, 150),
+            17,
+            Color::new(255, 255, 0, 0),
+        )]);
+
+        let drawer = SymbolArtDrawer::default();
+        let image = drawer.draw_with_scale(&sa, 2.0).unwrap();
+        let (rgba, width, height) = drawer.draw_raw(&sa, ChannelOrder::Rgba, 2.0).unwrap();
+        assert_eq!((width, height), image.dimensions());
+        assert_eq!(rgba, image.as_raw().as_slice());
+
+        let (bgra, ..) = drawer.draw_raw(&sa, ChannelOrder::Bgra, 2.0).unwrap();
+        assert_eq!(bgra.len(), rgba.len());
+        assert!(bgra.chunks_exact(4).any(|p| p == [0, 0, 255, 255]));
+        for (bgra, rgba) in bgra.chunks_exact(4).zip(rgba.chunks_exact(4)) {
+            assert_eq!(bgra, [rgba[2], rgba[1], rgba[0], rgba[3]]);
+        }
+    }
+
+    #[test]
+    fn test_drawer_draw_rgb() {
+        let green = Rgb([0, 255, 0]);
+        let sa = MockSymbolArt::with_layers(vec![MockLayer::new(
+            (100, 100),
+            (150, 150),
+            17,
+            Color::new(255, 255, 0, 0),
+        )]);
+
+        let drawer = SymbolArtDrawer::default();
+        let rgba = drawer.draw_with_scale(&sa, 2.0).unwrap();
+        let rgb = drawer.draw_rgb(&sa, green, 2.0).unwrap();
+
+        assert_eq!(rgb.dimensions(), rgba.dimensions());
+        assert_eq!(*rgb.get_pixel(0, 0), green);
+        for (rgb, rgba) in rgb.pixels().zip(rgba.pixels()) {
+            match rgba[3] {
+                0 => assert_eq!(*rgb, green),
+                255 => assert_eq!(*rgb, Rgb([255, 0, 0])),
+                _ => {}
+            }
+        }
+        assert!(rgba.pixels().any(|p| p[3] == 255));
+
+        let premultiplied = SymbolArtDrawer::default()
+            .with_premultiplied_alpha(true)
+            .draw_rgb(&sa, green, 2.0)
+            .unwrap();
+        assert_eq!(premultiplied, rgb);
+    }
+
+    #[test]
+    fn test_drawer_with_canvas_size() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let expected = SymbolArtDrawer::default().draw(&sa).unwrap();
+        let drawer = SymbolArtDrawer::default().with_canvas_size(512, 256);
+        let image = drawer.draw(&sa).unwrap();
+
+        assert_eq!(image.dimensions(), expected.dimensions());
+        assert_eq!(image.as_raw(), expected.as_raw());
+
+        // A canvas smaller than the view is grown to the view, so the layer in
+        // the corner of the default canvas is clipped off while the other stays
+        let red = Color::new(255, 255, 0, 0);
+        let sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((0, 0), (10, 10), 242, red),
+            MockLayer::new((100, 100), (150, 150), 242, red),
+        ]);
+        let drawer = SymbolArtDrawer::default().with_auto_crop(true);
+        let full = drawer.draw(&sa).unwrap();
+        let grown = drawer.with_canvas_size(100, 50).draw(&sa).unwrap();
+        assert!(
+            full.width() > 140 && full.height() > 140,
+            "{:?}",
+            full.dimensions()
+        );
+        assert!(
+            grown.width() <= 51 && grown.height() <= 51,
+            "{:?}",
+            grown.dimensions()
+        );
+        let grown = SymbolArtDrawer::default()
+            .with_canvas_size(100, 50)
+            .draw(&sa)
+            .unwrap();
+        assert_eq!(grown.dimensions(), (193, 96));
+
+        let drawer = SymbolArtDrawer::default().with_canvas_size(0, 256);
+        assert!(matches!(
+            drawer.draw(&sa),
+            Err(SARError::InvalidCanvasSize(0, 256))
+        ));
+    }
+
+    #[test]
+    fn test_drawer_with_background() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let background = Rgba([255, 255, 255, 255]);
+        let transparent = SymbolArtDrawer::default().draw(&sa).unwrap();
+        let image = SymbolArtDrawer::default()
+            .with_background(background)
+            .draw(&sa)
+            .unwrap();
+
+        assert_eq!(image.dimensions(), transparent.dimensions());
+        for (pixel, original) in image.pixels().zip(transparent.pixels()) {
+            assert_eq!(pixel[3], 255);
+            if original[3] == 0 {
+                assert_eq!(*pixel, background);
+            }
+        }
+    }
+
+    #[test]
+    fn test_drawer_with_interpolation() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let count_alphas = |image: &RgbaImage| {
+            image
+                .pixels()
+                .map(|p| p[3])
+                .collect::<std::collections::HashSet<_>>()
+                .len()
+        };
+        let nearest = SymbolArtDrawer::default()
+            .draw_with_scale(&sa, 4.0)
+            .unwrap();
+        let bilinear = SymbolArtDrawer::default()
+            .with_interpolation(Interpolation::Bilinear)
+            .draw_with_scale(&sa, 4.0)
+            .unwrap();
+
+        assert_eq!(bilinear.dimensions(), nearest.dimensions());
+        assert_ne!(bilinear.as_raw(), nearest.as_raw());
+        assert!(count_alphas(&bilinear) > count_alphas(&nearest));
+    }
+
+    #[test]
+    fn test_drawer_smooth_edges_without_halo() {
+        let assets = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let dir = std::env::temp_dir().join(format!("sar-core-draw-halo-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["symbols_r.png", "symbols_g.png", "symbols_b.png"] {
+            std::fs::copy(assets.join(file), dir.join(file)).unwrap();
+        }
+        // Symbol 721 is a white square on transparent black, drawn with its own colors
+        let mut sheet = RgbaImage::new(64 * 16, 64 * 4);
+        for y in 16..48 {
+            for x in 64 + 16..64 + 48 {
+                sheet.put_pixel(x, y, Rgba([255; 4]));
+            }
+        }
+        sheet.save(dir.join("symbols_color.png")).unwrap();
+        let resource = resource::Resource::from_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let sa = MockSymbolArt::with_layers(vec![MockLayer::new(
+            (80, 100),
+            (110, 130),
+            721,
+            Color::new(255, 0, 0, 0),
+        )]);
+        let image = SymbolArtDrawer::with_resource(resource)
+            .with_interpolation(Interpolation::Bilinear)
+            .with_background(Rgba([255; 4]))
+            .draw_with_scale(&sa, 8.0)
+            .unwrap();
+
+        // The edges are blended with white, not with transparent black
+        assert!(image.pixels().all(|p| p.0[..3] == [255; 3]));
+    }
+
+    #[test]
+    fn test_drawer_with_supersample() {
+        let sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((100, 100), (150, 140), 17, Color::new(255, 255, 0, 0)),
+            MockLayer::new((60, 90), (110, 110), 300, Color::new(255, 0, 0, 255)),
+        ]);
+        let count_alphas = |image: &RgbaImage| {
+            image
+                .pixels()
+                .map(|p| p[3])
+                .collect::<std::collections::HashSet<_>>()
+                .len()
+        };
+
+        let nearest = SymbolArtDrawer::default()
+            .draw_with_scale(&sa, 2.0)
+            .unwrap();
+        let once = SymbolArtDrawer::default()
+            .with_supersample(1)
+            .draw_with_scale(&sa, 2.0)
+            .unwrap();
+        assert_eq!(once, nearest);
+
+        let drawer = SymbolArtDrawer::default().with_supersample(4);
+        let supersampled = drawer.draw_with_scale(&sa, 2.0).unwrap();
+        assert_eq!(supersampled.dimensions(), nearest.dimensions());
+        assert!(count_alphas(&supersampled) > count_alphas(&nearest));
+
+        let clamped = SymbolArtDrawer::default()
+            .with_supersample(100)
+            .draw_with_scale(&sa, 2.0)
+            .unwrap();
+        assert_eq!(clamped, supersampled);
+        assert_eq!(
+            drawer.draw_single_layer(&sa, 0).unwrap().dimensions(),
+            (193, 96)
+        );
+    }
+
+    #[test]
+    fn test_drawer_with_view_larger_than_canvas() {
+        let sa = MockSymbolArt {
+            width: 250,
+            height: 96,
+            layers: vec![MockLayer::new(
+                (10, 100),
+                (40, 130),
+                1,
+                Color::new(255, 255, 0, 0),
+            )],
+        };
+
+        let drawer = SymbolArtDrawer::default()
+            .with_raise_error(true)
+            .with_canvas_size(128, 64);
+        let image = drawer.draw(&sa).unwrap();
+
+        assert_eq!(image.dimensions(), (250, 96));
+        assert!(image.pixels().any(|p| p[3] > 0));
+    }
+
+    #[test]
+    fn test_drawer_draw_into() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let drawer = SymbolArtDrawer::default();
+        let expected = drawer.draw(&sa).unwrap();
+        let mut target = RgbaImage::from_pixel(256, 256, Rgba([255; 4]));
+        drawer.draw_into(&sa, &mut target).unwrap();
+
+        let (width, height) = expected.dimensions();
+        let view = target.view(128 - width / 2, 128 - height / 2, width, height);
+        assert_eq!(view.to_image().as_raw(), expected.as_raw());
+        assert_eq!(*target.get_pixel(0, 0), Rgba([0; 4]));
+    }
+
+    #[test]
+    fn test_drawer_draw_into_empty_target() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let mut target = RgbaImage::new(0, 0);
+        let result = SymbolArtDrawer::default().draw_into(&sa, &mut target);
+
+        assert!(matches!(result, Err(SARError::InvalidCanvasSize(0, 0))));
+    }
+
+    #[test]
+    fn test_drawer_with_layer_alpha() {
+        let layer = MockLayer::new((100, 100), (150, 150), 1, Color::new(255, 255, 0, 0));
+        let mut sa = MockSymbolArt::with_layers(vec![layer]);
+
+        let drawer = SymbolArtDrawer::default().with_raise_error(true);
+        let opaque = drawer.draw(&sa).unwrap();
+        sa.layers[0].alpha = 0.5;
+        let faded = drawer.draw(&sa).unwrap();
+
+        let mut drawn = 0;
+        for (pixel, original) in faded.pixels().zip(opaque.pixels()) {
+            if original[3] > 0 {
+                assert_eq!(pixel[3], 128);
+                drawn += 1;
+            }
+        }
+        assert!(drawn > 0);
+    }
+
+    #[test]
+    fn test_drawer_draw_layers() {
+        let red = MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 255, 0, 0));
+        let blue = MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 0, 0, 255));
+        let sa = MockSymbolArt::with_layers(vec![red, blue]);
+
+        let drawer = SymbolArtDrawer::default().with_raise_error(true);
+        let full = drawer.draw(&sa).unwrap();
+        let clamped = drawer.draw_layers(&sa, 0..100).unwrap();
+        let bottom = drawer.draw_layers(&sa, 1..2).unwrap();
+        let empty = drawer.draw_layers(&sa, 5..10).unwrap();
+
+        assert_eq!(clamped.as_raw(), full.as_raw());
+        // The center of the layers, in view coordinates
+        let center = (125 - 32, 125 - 80);
+        assert_eq!(*full.get_pixel(center.0, center.1), Rgba([255, 0, 0, 255]));
+        assert_eq!(
+            *bottom.get_pixel(center.0, center.1),
+            Rgba([0, 0, 255, 255])
+        );
+        assert!(empty.pixels().all(|p| p[3] == 0));
+    }
+
+    #[test]
+    fn test_layer_transform() {
+        let corners =
+            MockLayer::new((100, 100), (140, 120), 17, Color::new(255, 0, 0, 0)).corners();
+        assert_eq!(
+            LayerTransform::default().apply(corners),
+            corners.map(|p| (p.x as f32, p.y as f32))
+        );
+
+        let transform = LayerTransform {
+            translate: (5.0, -10.0),
+            rotate: 90.0,
+            scale: (0.5, 2.0),
+        };
+        let moved = transform.apply(corners);
+        let expected = [(145.0, 90.0), (145.0, 110.0), (105.0, 110.0), (105.0, 90.0)];
+        for ((x, y), (ex, ey)) in moved.into_iter().zip(expected) {
+            assert!((x - ex).abs() < 1e-4 && (y - ey).abs() < 1e-4, "{moved:?}");
+        }
+    }
+
+    #[test]
+    fn test_drawer_draw_with_overrides() {
+        let layer = |x, y| MockLayer::new((x, y), (x + 40, y + 40), 17, Color::new(255, 255, 0, 0));
+        let sa = MockSymbolArt::with_layers(vec![layer(40, 100), layer(100, 120)]);
+        let moved = MockSymbolArt::with_layers(vec![layer(40, 100), layer(110, 110)]);
+
+        let drawer = SymbolArtDrawer::default();
+        let overrides = HashMap::from([(
+            1,
+            LayerTransform {
+                translate: (10.0, -10.0),
+                ..Default::default()
+            },
+        )]);
+        let image = drawer.draw_with_overrides(&sa, &overrides, 1.0).unwrap();
+        assert_eq!(image, drawer.draw(&moved).unwrap());
+        assert_ne!(image, drawer.draw(&sa).unwrap());
+
+        let unlisted = HashMap::from([(7, LayerTransform::default())]);
+        let image = drawer.draw_with_overrides(&sa, &unlisted, 1.0).unwrap();
+        assert_eq!(image, drawer.draw(&sa).unwrap());
+    }
+
+    #[test]
+    fn test_drawer_diff() {
+        let red = Color::new(255, 255, 0, 0);
+        let a = MockSymbolArt::with_layers(vec![MockLayer::new((40, 100), (80, 140), 17, red)]);
+        let mut b = MockSymbolArt::with_layers(vec![
+            MockLayer::new((150, 100), (190, 140), 17, red),
+            MockLayer::new((40, 100), (80, 140), 17, red),
+        ]);
+
+        let drawer = SymbolArtDrawer::default();
+        let highlighted = |image: &RgbaImage| {
+            image
+                .pixels()
+                .filter(|p| **p == Rgba([255, 0, 255, 255]))
+                .count()
+        };
+        let added = drawer.draw_single_layer(&b, 0).unwrap();
+        let diff = drawer.diff(&a, &b, 1.0).unwrap();
+        assert_eq!(diff.dimensions(), (193, 96));
+        assert_eq!(
+            highlighted(&diff),
+            added.pixels().filter(|p| p[3] > 0).count()
+        );
+        assert!(diff.pixels().any(|p| *p == Rgba([255, 0, 0, 64])));
+
+        b.layers.remove(0);
+        assert_eq!(highlighted(&drawer.diff(&a, &b, 1.0).unwrap()), 0);
+
+        b.height = 48;
+        let diff = drawer.diff(&a, &b, 1.0).unwrap();
+        assert_eq!(diff.dimensions(), (193, 96));
+        assert!(highlighted(&diff) > 0);
+    }
+
+    #[test]
+    fn test_drawer_draw_tile() {
+        let sa = crate::parse(RAW_FILE.to_vec()).unwrap();
+        for interpolation in [Interpolation::Nearest, Interpolation::Bilinear] {
+            let drawer = SymbolArtDrawer::default()
+                .with_interpolation(interpolation)
+                .with_background(Rgba([255, 255, 255, 255]));
+            let expected = drawer.draw(&sa).unwrap();
+
+            let mut stitched = RgbaImage::new(193, 96);
+            for (x, y) in [(0, 0), (100, 0), (0, 50), (100, 50)] {
+                let tile = drawer
+                    .draw_tile(&sa, 1.0, Rect::at(x, y).of_size(100, 50))
+                    .unwrap();
+                // The tiles on the right and the bottom are clipped to the output
+                assert_eq!(
+                    tile.dimensions(),
+                    (if x == 0 { 100 } else { 93 }, if y == 0 { 50 } else { 46 })
+                );
+                imageops::replace(&mut stitched, &tile, x as i64, y as i64);
+            }
+            if interpolation == Interpolation::Nearest {
+                assert_eq!(stitched, expected);
+            }
+            // Smooth interpolation samples at slightly different positions
+            for (a, b) in stitched.pixels().zip(expected.pixels()) {
+                assert!(a.0.iter().zip(b.0).all(|(a, b)| a.abs_diff(b) <= 2));
+            }
+        }
+
+        let drawer = SymbolArtDrawer::default();
+        let outside = drawer.draw_tile(&sa, 1.0, Rect::at(193, 0).of_size(10, 10));
+        assert!(matches!(outside, Err(SARError::InvalidCanvasSize(10, 10))));
+    }
+
+    #[test]
+    fn test_is_off_canvas() {
+        let square = |x: f32, y: f32| [(x, y), (x + 10.0, y), (x + 10.0, y + 10.0), (x, y + 10.0)];
+        assert!(!SymbolArtDrawer::is_off_canvas(
+            square(10.0, 10.0),
+            (64, 64)
+        ));
+        assert!(!SymbolArtDrawer::is_off_canvas(
+            square(-11.0, 10.0),
+            (64, 64)
+        ));
+        assert!(SymbolArtDrawer::is_off_canvas(
+            square(-13.0, 10.0),
+            (64, 64)
+        ));
+        assert!(SymbolArtDrawer::is_off_canvas(square(10.0, 67.0), (64, 64)));
+
+        // A twisted layer can be projected past its corners
+        let [a, b, c, d] = square(-30.0, 10.0);
+        assert!(!SymbolArtDrawer::is_off_canvas([a, b, d, c], (64, 64)));
+    }
+
+    #[test]
+    fn test_drawer_with_symbol_override() {
+        let layer = |symbol_id| {
+            MockLayer::new(
+                (100, 100),
+                (150, 150),
+                symbol_id,
+                Color::new(255, 255, 0, 0),
+            )
+        };
+        let sa = MockSymbolArt::with_layers(vec![layer(17), layer(721)]);
+        let textured = RgbaImage::from_pixel(64, 32, Rgba([0, 255, 0, 255]));
+        let colored = RgbaImage::from_pixel(64, 64, Rgba([0, 0, 255, 255]));
+
+        // The square spans 69..119 and 20..70 of the view
+        let inside = |image: &RgbaImage| {
+            (70..118)
+                .flat_map(|x| (21..69).map(move |y| (x, y)))
+                .map(|(x, y)| *image.get_pixel(x, y))
+                .collect::<Vec<_>>()
+        };
+        let drawer = SymbolArtDrawer::default().with_symbol_override(SymbolId::new(17), textured);
+        let image = drawer.draw_single_layer(&sa, 0).unwrap();
+        assert!(inside(&image).iter().all(|p| p.0 == [255, 0, 0, 255]));
+
+        let drawer = drawer.with_symbol_override(SymbolId::new(721), colored);
+        let image = drawer.draw_single_layer(&sa, 1).unwrap();
+        assert!(inside(&image).iter().all(|p| p.0 == [0, 0, 255, 255]));
+        // The last layer is drawn beneath the first
+        let image = drawer.draw(&sa).unwrap();
+        assert!(inside(&image).iter().all(|p| p.0 == [255, 0, 0, 255]));
+        assert_ne!(image, SymbolArtDrawer::default().draw(&sa).unwrap());
+    }
+
+    #[test]
+    fn test_drawer_non_square_symbol() {
+        let sa = MockSymbolArt::with_layers(vec![MockLayer::new(
+            (100, 100),
+            (150, 150),
+            721,
+            Color::new(255, 255, 255, 255),
+        )]);
+        // A different color in each 32x16 quadrant of a 64x32 symbol
+        let quadrants = [
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [255, 255, 0, 255],
+        ];
+        let symbol = RgbaImage::from_fn(64, 32, |x, y| {
+            Rgba(quadrants[(x / 32 + y / 16 * 2) as usize])
+        });
+        let drawer = SymbolArtDrawer::default().with_symbol_override(SymbolId::new(721), symbol);
+        assert_eq!(drawer.symbol_dimensions(SymbolId::new(721)), Some((64, 32)));
+        assert_eq!(drawer.symbol_dimensions(SymbolId::new(17)), Some((64, 64)));
+        assert_eq!(drawer.symbol_dimensions(SymbolId::new(0)), None);
+
+        // The square spans 69..119 and 20..70 of the view, split at 94 and 45
+        let image = drawer.draw(&sa).unwrap();
+        for (i, (xs, ys)) in [
+            (70..92, 21..43),
+            (96..118, 21..43),
+            (70..92, 47..69),
+            (96..118, 47..69),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            for (x, y) in xs.flat_map(|x| ys.clone().map(move |y| (x, y))) {
+                assert_eq!(image.get_pixel(x, y).0, quadrants[i], "at ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_drawer_draw_single_layer() {
+        let red = MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 255, 0, 0));
+        let blue = MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 0, 0, 255));
+        let missing = MockLayer::new((100, 100), (150, 150), 0, Color::new(255, 0, 0, 255));
+        let sa = MockSymbolArt::with_layers(vec![red, blue, missing]);
+
+        let drawer = SymbolArtDrawer::default().with_background(Rgba([255; 4]));
+        let bottom = drawer.draw_single_layer(&sa, 1).unwrap();
+
+        assert_eq!(
+            *bottom.get_pixel(125 - 32, 125 - 80),
+            Rgba([0, 0, 255, 255])
+        );
+        assert_eq!(*bottom.get_pixel(0, 0), Rgba([0; 4]));
+        assert!(matches!(
+            drawer.draw_single_layer(&sa, 2),
+            Err(SARError::SymbolNotFound(_))
+        ));
+        assert!(matches!(
+            drawer.draw_single_layer(&sa, 3),
+            Err(SARError::LayerOutOfRange(3, 3))
+        ));
+
+        let scaled = drawer.draw_single_layer_with_scale(&sa, 0, 2.0).unwrap();
+        assert_eq!(scaled.dimensions(), (386, 192));
+        assert_eq!(
+            scaled.get_pixel(250 - 62, 250 - 160),
+            drawer
+                .draw_single_layer(&sa, 0)
+                .unwrap()
+                .get_pixel(125 - 31, 125 - 80)
+        );
+    }
+
+    #[test]
+    fn test_drawer_draw_staged() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let drawer = SymbolArtDrawer::default().with_chunk_size(30);
+        let frames = drawer.draw_staged(&sa, 1.0).unwrap();
+        let expected = drawer.draw(&sa).unwrap();
+
+        assert_eq!(frames.len(), sa.layers().len().div_ceil(30));
+        assert_eq!(frames.last().unwrap().as_raw(), expected.as_raw());
+        for (previous, frame) in frames.iter().zip(frames.iter().skip(1)) {
+            for (before, after) in previous.pixels().zip(frame.pixels()) {
+                assert!(before[3] == 0 || after[3] > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_drawer_with_auto_crop() {
+        let layer = MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 255, 0, 0));
+        let mut sa = MockSymbolArt::with_layers(vec![layer]);
+
+        let drawer = SymbolArtDrawer::default().with_auto_crop(true);
+        let image = drawer.draw_with_scale(&sa, 2.0).unwrap();
+
+        let (width, height) = image.dimensions();
+        assert!(width < 100 && height < 100);
+        assert!((0..width).any(|x| image.get_pixel(x, 0)[3] > 0));
+        assert!((0..height).any(|y| image.get_pixel(0, y)[3] > 0));
+        assert!((0..width).any(|x| image.get_pixel(x, height - 1)[3] > 0));
+        assert!((0..height).any(|y| image.get_pixel(width - 1, y)[3] > 0));
+
+        sa.layers[0].is_hidden = true;
+        let empty = drawer.draw(&sa).unwrap();
+        assert_eq!(empty.dimensions(), (1, 1));
+        assert_eq!(*empty.get_pixel(0, 0), Rgba([0; 4]));
+    }
+
+    #[test]
+    fn test_drawer_with_padding() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let drawer = SymbolArtDrawer::default();
+        let padded = SymbolArtDrawer::default().with_padding(16);
+        for scale in [1.0, 2.0] {
+            let image = drawer.draw_with_scale(&sa, scale).unwrap();
+            let padded = padded.draw_with_scale(&sa, scale).unwrap();
+
+            assert_eq!(padded.width(), image.width() + 32);
+            assert_eq!(padded.height(), image.height() + 32);
+            assert_eq!(
+                padded
+                    .view(16, 16, image.width(), image.height())
+                    .to_image(),
+                image
+            );
+        }
+    }
+
+    #[test]
+    fn test_drawer_with_flip() {
+        let layer = MockLayer::new((40, 90), (90, 140), 17, Color::new(255, 255, 0, 0));
+        let sa = MockSymbolArt::with_layers(vec![layer]);
+
+        let image = SymbolArtDrawer::default().draw(&sa).unwrap();
+        let flipped = SymbolArtDrawer::default()
+            .with_flip(true, true)
+            .draw(&sa)
+            .unwrap();
+
+        let (width, height) = image.dimensions();
+        assert_ne!(flipped, image);
+        for (x, y, pixel) in image.enumerate_pixels() {
+            assert_eq!(flipped.get_pixel(width - 1 - x, height - 1 - y), pixel);
+        }
+    }
+
+    #[test]
+    fn test_drawer_with_grayscale() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let image = SymbolArtDrawer::default().draw(&sa).unwrap();
+        let grayscale = SymbolArtDrawer::default()
+            .with_grayscale(true)
+            .draw(&sa)
+            .unwrap();
+
+        assert_eq!(grayscale.dimensions(), image.dimensions());
+        for (pixel, original) in grayscale.pixels().zip(image.pixels()) {
+            assert!(pixel[0] == pixel[1] && pixel[1] == pixel[2]);
+            assert_eq!(pixel[3], original[3]);
+        }
+    }
+
+    #[test]
+    fn test_drawer_with_tint() {
+        let layer = MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 200, 100, 50));
+        let sa = MockSymbolArt::with_layers(vec![layer]);
+
+        let image = SymbolArtDrawer::default()
+            .with_tint(Rgba([255, 0, 128, 255]))
+            .draw(&sa)
+            .unwrap();
+
+        assert_eq!(
+            *image.get_pixel(125 - 32, 125 - 80),
+            Rgba([200, 0, 25, 255])
+        );
+    }
+
+    #[test]
+    fn test_drawer_with_checkerboard() {
+        let red = Color::new(255, 255, 0, 0);
+        let sa = MockSymbolArt::with_layers(vec![MockLayer::new((100, 100), (150, 150), 17, red)]);
+
+        let drawer = SymbolArtDrawer::default().with_checkerboard(8);
+        let image = drawer.draw(&sa).unwrap();
+        assert!(image.pixels().all(|pixel| pixel[3] == 255));
+        assert_eq!(*image.get_pixel(125 - 32, 125 - 80), Rgba([255, 0, 0, 255]));
+        let [light, dark] = effect::CHECKERBOARD_COLORS;
+        assert_eq!(*image.get_pixel(0, 0), light);
+        assert_eq!(*image.get_pixel(8, 0), dark);
+
+        let tile = drawer
+            .draw_tile(&sa, 1.0, Rect::at(4, 0).of_size(8, 8))
+            .unwrap();
+        assert_eq!(tile, imageops::crop_imm(&image, 4, 0, 8, 8).to_image());
+
+        let fitted = drawer.draw_fit(&sa, 400, 400).unwrap();
+        assert!(fitted.pixels().all(|pixel| pixel[3] == 255));
+        assert_eq!(*fitted.get_pixel(8, 0), dark);
+
+        let disabled = SymbolArtDrawer::default().with_checkerboard(0);
+        assert_eq!(
+            disabled.draw(&sa).unwrap(),
+            SymbolArtDrawer::default().draw(&sa).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_drawer_with_quantize() {
+        use std::collections::HashSet;
+
+        let sa = parse(Vec::from(RAW_FILE)).unwrap();
+        let drawer = SymbolArtDrawer::default().with_interpolation(Interpolation::Bilinear);
+        let image = drawer.draw(&sa).unwrap();
+        let count = |image: &RgbaImage| image.pixels().collect::<HashSet<_>>().len();
+        assert!(count(&image) > 16);
+
+        let drawer = drawer.with_quantize(16);
+        let quantized = drawer.draw(&sa).unwrap();
+        assert!(count(&quantized) <= 16, "{}", count(&quantized));
+        assert_eq!(quantized, drawer.draw(&sa).unwrap());
+        for (pixel, original) in quantized.pixels().zip(image.pixels()) {
+            assert_eq!(pixel[3] == 0, original[3] == 0);
+        }
+
+        let flat = SymbolArtDrawer::default().draw(&sa).unwrap();
+        let kept = SymbolArtDrawer::default()
+            .with_quantize(count(&flat))
+            .draw(&sa)
+            .unwrap();
+        assert_eq!(kept, flat);
+    }
+
+    #[test]
+    fn test_drawer_draw_all() {
+        let art = |count| {
+            MockSymbolArt::with_layers(vec![
+                MockLayer::new(
+                    (100, 100),
+                    (150, 150),
+                    17,
+                    Color::new(255, 255, 0, 0)
+                );
+                count
+            ])
+        };
+        let arts = [art(1), art(11), art(2), art(3)];
+
+        let drawer = SymbolArtDrawer::default().with_max_layers(10);
+        for drawer in [drawer.clone(), drawer.with_single_threaded(true)] {
+            let results = drawer.draw_all(&arts, 0.5);
+            assert_eq!(results.len(), arts.len());
+            for (result, sa) in results.into_iter().zip(&arts) {
+                match result {
+                    Ok(image) => assert_eq!(image, drawer.draw_with_scale(sa, 0.5).unwrap()),
+                    Err(e) => {
+                        assert!(matches!(e, SARError::TooManyLayers(11, 10)));
+                        assert_eq!(sa.layers.len(), 11);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_drawer_draw_with_cancel() {
+        static CANCEL: AtomicBool = AtomicBool::new(false);
+
+        /// A layer that sets `CANCEL` when it is drawn
+        #[derive(Clone)]
+        struct CancellingLayer(MockLayer);
+
+        impl SymbolArtLayer for CancellingLayer {
+            fn top_left(&self) -> Position {
+                self.0.top_left()
+            }
+
+            fn bottom_left(&self) -> Position {
+                self.0.bottom_left()
+            }
+
+            fn top_right(&self) -> Position {
+                self.0.top_right()
+            }
+
+            fn bottom_right(&self) -> Position {
+                self.0.bottom_right()
+            }
+
+            fn symbol(&self) -> crate::core::symbol::Symbol {
+                CANCEL.store(true, Ordering::Relaxed);
+                self.0.symbol()
+            }
+
+            fn color(&self) -> Color {
+                self.0.color()
+            }
+
+            fn is_hidden(&self) -> bool {
+                self.0.is_hidden()
+            }
+        }
+
+        struct CancellingArt(Vec<CancellingLayer>);
+
+        impl SymbolArt for CancellingArt {
+            type Layer = CancellingLayer;
+
+            fn author_id(&self) -> u32 {
+                0
+            }
+
+            fn height(&self) -> u8 {
+                96
+            }
+
+            fn width(&self) -> u8 {
+                193
+            }
+
+            fn layers(&self) -> Vec<Self::Layer> {
+                self.0.clone()
+            }
+
+            fn name(&self) -> &str {
+                ""
+            }
+        }
+
+        let layer = MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 255, 0, 0));
+        let sa = MockSymbolArt::with_layers(vec![layer.clone(); 3]);
+        let drawer = SymbolArtDrawer::default().with_chunk_size(1);
+        let flag = AtomicBool::new(false);
+        assert_eq!(
+            drawer.draw_with_cancel(&sa, 1.0, &flag).unwrap(),
+            drawer.draw(&sa).unwrap()
+        );
+        flag.store(true, Ordering::Relaxed);
+        assert!(matches!(
+            drawer.draw_with_cancel(&sa, 1.0, &flag),
+            Err(SARError::Cancelled)
+        ));
+
+        // The first chunk sets the flag, and the next ones stop before their layer
+        let sa = CancellingArt(vec![CancellingLayer(layer.clone()); 3]);
+        let drawer = drawer.with_single_threaded(true);
+        assert!(matches!(
+            drawer.draw_with_cancel(&sa, 1.0, &CANCEL),
+            Err(SARError::Cancelled)
+        ));
+
+        // The flag is set while the last layer is drawn, so nothing is stopped
+        CANCEL.store(false, Ordering::Relaxed);
+        let sa = CancellingArt(vec![CancellingLayer(layer.clone())]);
+        assert_eq!(
+            drawer.draw_with_cancel(&sa, 1.0, &CANCEL).unwrap(),
+            drawer
+                .draw(&MockSymbolArt::with_layers(vec![layer]))
+                .unwrap()
+        );
+        assert!(CANCEL.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_drawer_draw_animation() {
+        use image::{codecs::gif::GifDecoder, AnimationDecoder};
+
+        let sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 255, 0, 0)),
+            MockLayer::new((60, 90), (110, 140), 17, Color::new(255, 0, 0, 255)),
+        ]);
+
+        let drawer = SymbolArtDrawer::default().with_chunk_size(1);
+        let bytes = drawer.draw_animation(&sa, 100).unwrap();
+
+        let decoder = GifDecoder::new(std::io::Cursor::new(bytes)).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].delay().numer_denom_ms(), (100, 1));
+        assert_eq!(frames[2].delay().numer_denom_ms(), (ANIMATION_HOLD_MS, 1));
+        assert_eq!(frames[2].buffer().dimensions(), (193, 96));
+    }
+
+    #[test]
+    fn test_drawer_to_svg() {
+        let mut hidden = MockLayer::new((0, 0), (255, 255), 17, Color::new(255, 0, 0, 255));
+        hidden.is_hidden = true;
+        let sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 255, 0, 0)),
+            hidden,
+        ]);
+
+        let svg = SymbolArtDrawer::default().to_svg(&sa).unwrap();
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(r#"viewBox="32 80 193 96""#));
+        assert_eq!(svg.matches("<image").count(), 1);
+        assert!(svg.contains(r#"transform="matrix(0.78125 0 0 0.78125 100 100)""#));
+        assert!(!svg.contains("<rect"));
+
+        let svg = SymbolArtDrawer::default()
+            .with_background(Rgba([255, 255, 255, 255]))
+            .to_svg(&sa)
+            .unwrap();
+        assert!(svg.contains(r#"fill="rgb(255,255,255)""#));
+    }
+
+    #[cfg(feature = "gpu")]
+    #[test]
+    fn test_drawer_with_gpu() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let expected = SymbolArtDrawer::default().draw(&sa).unwrap();
+        let drawer = SymbolArtDrawer::default().with_gpu();
+        let image = drawer.draw(&sa).unwrap();
+
+        assert_eq!(image.dimensions(), expected.dimensions());
+        if !drawer.uses_gpu() {
+            assert_eq!(image, expected);
+            return;
+        }
+
+        // Edges may land on the other side of a pixel, and blending rounds differently
+        let differing = image
+            .pixels()
+            .zip(expected.pixels())
+            .filter(|(a, b)| a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > 2))
+            .count();
+        assert!(differing * 100 < expected.len() / 4, "{differing}");
+    }
+
+    #[test]
+    fn test_drawer_try_new() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let drawer = SymbolArtDrawer::try_new().unwrap();
+        let image = drawer.draw(&sa).unwrap();
+
+        assert_eq!(image, SymbolArtDrawer::new().draw(&sa).unwrap());
+    }
+
+    #[test]
+    fn test_drawer_with_zero_canvas_size() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let drawer = SymbolArtDrawer::default().with_canvas_size(0, 256);
+        let result = drawer.draw(&sa);
+
+        assert!(matches!(result, Err(SARError::InvalidCanvasSize(0, 256))));
+    }
+
+    /// Overlays of a translucent 200-layer art, one per layer
+    fn many_overlays() -> (RgbaImage, Vec<RgbaImage>) {
+        let layers = (0..200u32)
+            .map(|i| {
+                let (x, y) = ((i * 7 % 150) as u8, (i * 13 % 150) as u8);
+                let color = Color::new(255, (i * 31 % 256) as u8, (i * 57 % 256) as u8, 128);
+                let mut layer = MockLayer::new((x, y), (x + 60, y + 60), 17 + i % 60, color);
+                layer.alpha = 0.3 + (i % 7) as f32 / 10.0;
+                layer
+            })
+            .collect::<Vec<_>>();
+
+        let canvas = RgbaImage::from_pixel(256, 256, Rgba([10, 20, 30, 200]));
+        let overlays = SymbolArtDrawer::default()
+            .with_chunk_size(1)
+            .draw_overlays(&layers, 0, 1.0, canvas.dimensions())
+            .unwrap();
+        (canvas, overlays)
+    }
+
+    fn overlay_sequential(canvas: &mut RgbaImage, overlays: &[RgbaImage]) {
+        for overlay in overlays {
+            imageops::overlay(canvas, overlay, 0, 0);
+        }
+    }
+
+    #[test]
+    fn test_overlay_all() {
+        let (canvas, overlays) = many_overlays();
+
+        let mut expected = canvas.clone();
+        overlay_sequential(&mut expected, &overlays);
+        let mut actual = canvas;
+        SymbolArtDrawer::default().overlay_all(&mut actual, &overlays);
+
+        assert_eq!(actual, expected);
+    }
+
+    /// Compares the compositing step against the sequential `imageops::overlay` loop
+    ///
+    /// Run with `cargo test --release -p sar-core bench_overlay_all -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_overlay_all() {
+        use std::time::Instant;
+
+        const ROUNDS: u32 = 20;
+        let (canvas, overlays) = many_overlays();
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            overlay_sequential(&mut canvas.clone(), &overlays);
+        }
+        let sequential = start.elapsed() / ROUNDS;
+
+        let drawer = SymbolArtDrawer::default();
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            drawer.overlay_all(&mut canvas.clone(), &overlays);
+        }
+        let parallel = start.elapsed() / ROUNDS;
+
+        println!(
+            "200 overlays on {} threads: sequential {sequential:?}, parallel {parallel:?} ({:.2}x)",
+            rayon::current_num_threads(),
+            sequential.as_secs_f64() / parallel.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn test_drawer_render_mipmaps() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+        let scales = [1.0, 0.5, 0.25];
+
+        let drawer = SymbolArtDrawer::default();
+        let mipmaps = drawer.render_mipmaps(&sa, &scales).unwrap();
+        let cached = drawer.projection_cache.as_ref().unwrap().len();
+        assert!(cached > 0);
+        assert_eq!(drawer.render_mipmaps(&sa, &scales).unwrap(), mipmaps);
+        assert_eq!(drawer.projection_cache.as_ref().unwrap().len(), cached);
+
+        let uncached = SymbolArtDrawer::default().with_projection_cache(false);
+        assert!(uncached.projection_cache.is_none());
+        for (mipmap, scale) in mipmaps.iter().zip(scales) {
+            assert_eq!(*mipmap, uncached.draw_with_scale(&sa, scale).unwrap());
+        }
+        assert_eq!(mipmaps[1].dimensions(), (96, 48));
+    }
+
+    #[test]
+    fn test_drawer_reuses_scratch_buffers() {
+        let layers = (0..200u32)
+            .map(|i| {
+                let (x, y) = ((i * 7 % 150) as u8, (i * 13 % 150) as u8);
+                MockLayer::new((x, y), (x + 60, y + 60), 17, Color::new(255, 255, 0, 0))
+            })
+            .collect();
+        let sa = MockSymbolArt::with_layers(layers);
+
+        let drawer = SymbolArtDrawer::default();
+        let image = drawer.draw(&sa).unwrap();
+
+        // Each of the 20 chunks holds at most one buffer at a time
+        assert!(drawer.scratch.allocations() <= 20);
+        assert_eq!(image, SymbolArtDrawer::default().draw(&sa).unwrap());
+    }
+
+    #[test]
+    fn test_drawer_content_bounds() {
+        use imageproc::rect::Region;
+
+        let layer = MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 255, 0, 0));
+        let mut sa = MockSymbolArt::with_layers(vec![layer]);
+
+        let drawer = SymbolArtDrawer::default();
+        let bounds = drawer.content_bounds(&sa, 1.0).unwrap().unwrap();
+        assert_eq!(bounds, Rect::at(68, 20).of_size(50, 50));
+
+        // The pixels drawn at scale 2.0 fall within the bounds
+        let bounds = drawer.content_bounds(&sa, 2.0).unwrap().unwrap();
+        assert_eq!(bounds, Rect::at(137, 40).of_size(100, 100));
+        let image = drawer.draw_with_scale(&sa, 2.0).unwrap();
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if pixel[3] > 0 {
+                assert!(bounds.contains(x as i32, y as i32), "{x}, {y}");
+            }
+        }
+
+        let drawer = SymbolArtDrawer::default()
+            .with_padding(10)
+            .with_flip(true, false);
+        let bounds = drawer.content_bounds(&sa, 1.0).unwrap().unwrap();
+        assert_eq!(bounds, Rect::at(213 - 78 - 50, 30).of_size(50, 50));
+
+        sa.layers[0].is_hidden = true;
+        assert_eq!(drawer.content_bounds(&sa, 1.0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_drawer_projection_error() {
+        let color = Color::new(255, 255, 0, 0);
+        let sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((100, 100), (150, 150), 17, color),
+            MockLayer::new((100, 100), (150, 150), 17, color),
+            MockLayer::new((120, 120), (120, 120), 18, color),
+        ]);
+
+        let drawer = SymbolArtDrawer::default()
+            .with_raise_error(true)
+            .with_chunk_size(2);
+        let result = drawer.draw(&sa);
+        assert!(
+            matches!(result, Err(SARError::ProjectionError(2, id, _, to)) if id.id() == 18 && to[0] == to[2]),
+            "{result:?}"
+        );
+
+        let result = drawer.draw_layers(&sa, 1..3);
+        assert!(matches!(result, Err(SARError::ProjectionError(2, ..))));
+        let result = drawer.draw_single_layer(&sa, 2);
+        assert!(matches!(result, Err(SARError::ProjectionError(2, ..))));
+    }
+
+    #[test]
+    fn test_drawer_draw_collect_errors() {
+        let color = Color::new(255, 255, 0, 0);
+        let sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((100, 100), (150, 150), 17, color),
+            MockLayer::new((100, 100), (150, 150), 0, color),
+            MockLayer::new((60, 60), (110, 110), 17, color),
+            MockLayer::new((120, 120), (120, 120), 18, color),
+            MockLayer::new((100, 100), (150, 150), 100, color),
+        ]);
+
+        let drawer = SymbolArtDrawer::default().with_chunk_size(2);
+        let (image, errors) = drawer.draw_collect_errors(&sa);
+
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(errors[0], SARError::SymbolNotFound(id) if id.id() == 0));
+        assert!(matches!(errors[1], SARError::ProjectionError(3, ..)));
+        assert!(matches!(errors[2], SARError::SymbolNotFound(id) if id.id() == 100));
+        // The layers that could be drawn match a suppressed render
+        assert_eq!(image, drawer.draw(&sa).unwrap());
+        assert!(drawer.with_raise_error(true).draw(&sa).is_err());
+    }
+
+    #[test]
+    fn test_drawer_draw_fit() {
+        let bytes = Vec::from(RAW_FILE);
+        let sa = parse(bytes).unwrap();
+
+        let drawer = SymbolArtDrawer::default();
+        let image = drawer.draw_fit(&sa, 512, 512).unwrap();
+        assert_eq!(image.dimensions(), (512, 512));
+        // The art is 193x96, so it fills the width and is centered vertically
+        let scaled = drawer.draw_with_scale(&sa, 512.0 / 193.0).unwrap();
+        let top = (512 - scaled.height()) / 2;
+        assert_eq!(
+            image
+                .view(0, top, scaled.width(), scaled.height())
+                .to_image(),
+            scaled
+        );
+        assert!((0..512).all(|x| image.get_pixel(x, top - 1)[3] == 0));
+
+        let capped = drawer
+            .with_max_fit_scale(2.0)
+            .draw_fit(&sa, 512, 512)
+            .unwrap();
+        let scaled = SymbolArtDrawer::default()
+            .draw_with_scale(&sa, 2.0)
+            .unwrap();
+        let (left, top) = ((512 - 386) / 2, (512 - 192) / 2);
+        assert_eq!(capped.view(left, top, 386, 192).to_image(), scaled);
+
+        let drawer = SymbolArtDrawer::default().with_padding(10);
+        assert!(matches!(
+            drawer.draw_fit(&sa, 20, 512),
+            Err(SARError::InvalidCanvasSize(20, 512))
+        ));
+    }
+
+    #[test]
+    fn test_drawer_empty_art() {
+        use crate::{write, SymbolArtBuilder, SymbolId};
+
+        let transparent = |image: &RgbaImage| image.pixels().all(|p| p.0 == [0, 0, 0, 0]);
+        let drawer = SymbolArtDrawer::default().with_raise_error(true);
+
+        let empty = parse(write(&SymbolArtBuilder::new().build().unwrap()).unwrap()).unwrap();
+        assert!(empty.layers().is_empty());
+        let image = drawer.draw(&empty).unwrap();
+        assert_eq!(image.dimensions(), (193, 96));
+        assert!(transparent(&image));
+        let image = drawer.draw_fit(&empty, 64, 64).unwrap();
+        assert_eq!(image.dimensions(), (64, 64));
+        assert!(transparent(&image));
+
+        let hidden = SymbolArtBuilder::new()
+            .add_hidden_layer(
+                SymbolId::new(17),
+                Color::new(255, 255, 0, 0),
+                [(100, 100), (150, 100), (150, 150), (100, 150)],
+            )
+            .build()
+            .unwrap();
+        assert!(transparent(&drawer.draw(&hidden).unwrap()));
+        let cropped = drawer.clone().with_auto_crop(true).draw(&hidden).unwrap();
+        assert_eq!(cropped.dimensions(), (1, 1));
+        assert!(transparent(&cropped));
+
+        // Neither an art without a size nor a tiny scale gives an empty image
+        let sizeless = MockSymbolArt {
+            width: 0,
+            height: 0,
+            layers: vec![],
+        };
+        assert_eq!(drawer.draw(&sizeless).unwrap().dimensions(), (1, 1));
+        assert_eq!(
+            drawer.draw_with_scale(&empty, 0.001).unwrap().dimensions(),
+            (1, 1)
+        );
+        let png = drawer.draw_png(&sizeless, 1.0).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (1, 1));
+        assert!(transparent(&image));
+
+        let red = Rgba([255, 0, 0, 255]);
+        let image = drawer
+            .with_background(red)
+            .draw_fit(&sizeless, 64, 32)
+            .unwrap();
+        assert_eq!(image.dimensions(), (64, 32));
+        assert!(image.pixels().all(|p| *p == red));
+    }
+
+    #[test]
+    fn test_drawer_with_max_layers() {
+        let layer = MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 255, 0, 0));
+        let sa = MockSymbolArt::with_layers(vec![layer; 226]);
+
+        let drawer = SymbolArtDrawer::default();
+        assert!(matches!(
+            drawer.draw(&sa),
+            Err(SARError::TooManyLayers(226, 225))
+        ));
+        assert!(matches!(
+            drawer.draw_layers(&sa, 0..1),
+            Err(SARError::TooManyLayers(226, 225))
+        ));
+        let (_, errors) = drawer.draw_collect_errors(&sa);
+        assert!(matches!(errors[..], [SARError::TooManyLayers(226, 225)]));
+
+        let drawer = drawer.with_max_layers(300);
+        assert!(drawer.draw(&sa).is_ok());
+        assert!(matches!(
+            drawer.with_max_layers(10).to_svg(&sa),
+            Err(SARError::TooManyLayers(226, 10))
+        ));
+    }
+
+    #[test]
+    fn test_drawer_draw_with_report() {
+        let color = Color::new(255, 255, 0, 0);
+        let mut sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((100, 100), (150, 150), 17, color),
+            MockLayer::new((100, 100), (150, 150), 0, color),
+            MockLayer::new((60, 60), (110, 110), 17, color),
+            MockLayer::new((120, 120), (120, 120), 18, color),
+            MockLayer::new((100, 100), (150, 150), 100, color),
+        ]);
+        sa.layers[2].is_hidden = true;
+
+        let drawer = SymbolArtDrawer::default().with_chunk_size(2);
+        let (image, report) = drawer.draw_with_report(&sa, 1.0).unwrap();
+        assert_eq!(
+            report,
+            RenderReport {
+                hidden: 1,
+                missing_symbol: 2,
+                projection_failure: 1,
+                off_canvas: 0,
+            }
+        );
+        assert!(report.is_degraded());
+        assert_eq!(image, drawer.draw(&sa).unwrap());
+
+        let drawer = drawer.with_raise_error(true);
+        assert!(drawer.draw_with_report(&sa, 1.0).is_err());
+        sa.layers.truncate(1);
+        let (image, report) = drawer.draw_with_report(&sa, 2.0).unwrap();
+        assert!(!report.is_degraded());
+        assert_eq!(image, drawer.draw_with_scale(&sa, 2.0).unwrap());
+    }
+
+    #[test]
+    fn test_drawer_off_canvas_layers() {
+        let color = Color::new(255, 255, 0, 0);
+        let mut sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((100, 100), (150, 150), 17, color),
+            // Above the view, which starts at y = 80
+            MockLayer::new((100, 10), (150, 60), 17, color),
+            // Across its left edge at x = 31
+            MockLayer::new((10, 100), (40, 120), 17, color),
+            // Left of the view, and outside of the canvas when it is 128x128
+            MockLayer::new((0, 100), (20, 120), 17, color),
+        ]);
+
+        let drawer = SymbolArtDrawer::default();
+        assert_eq!(drawer.off_canvas_layers(&sa, 1.0).unwrap(), [1, 3]);
+        let (_, report) = drawer.draw_with_report(&sa, 1.0).unwrap();
+        assert_eq!(report.off_canvas, 2);
+        assert!(!report.is_degraded());
+
+        sa.layers[1].is_hidden = true;
+        assert_eq!(drawer.off_canvas_layers(&sa, 2.0).unwrap(), [3]);
+
+        // The auto-cropped output keeps the whole canvas
+        let drawer = SymbolArtDrawer::default().with_auto_crop(true);
+        assert!(drawer.off_canvas_layers(&sa, 1.0).unwrap().is_empty());
+        let drawer = drawer.with_canvas_size(128, 128);
+        assert_eq!(drawer.off_canvas_layers(&sa, 1.0).unwrap(), [3]);
+    }
+
+    #[test]
+    fn test_drawer_blend_modes() {
+        let layer = |color| MockLayer::new((100, 100), (150, 150), 17, color);
+        let red = layer(Color::new(255, 255, 0, 0));
+        let blue = layer(Color::new(255, 0, 0, 255));
+        let drawer = SymbolArtDrawer::default().with_chunk_size(1);
+        let draw = |layers| {
+            let sa = MockSymbolArt::with_layers(layers);
+            drawer.draw(&sa).unwrap()
+        };
+
+        let (red_only, blue_only) = (draw(vec![red.clone()]), draw(vec![blue.clone()]));
+        for (mode, expected) in [
+            (BlendMode::Normal, Rgba([255, 0, 0, 255])),
+            (BlendMode::Multiply, Rgba([0, 0, 0, 255])),
+            (BlendMode::Screen, Rgba([255, 0, 255, 255])),
+            (BlendMode::Additive, Rgba([255, 0, 255, 255])),
+        ] {
+            let mut top = red.clone();
+            top.blend_mode = mode;
+            // The layers are in different chunks, which must not change the result
+            let image = draw(vec![top, blue.clone()]);
+
+            let mut overlapping = 0;
+            for (x, y, pixel) in image.enumerate_pixels() {
+                if red_only.get_pixel(x, y)[3] == 255 && blue_only.get_pixel(x, y)[3] == 255 {
+                    assert_eq!(*pixel, expected, "{mode:?} at ({x}, {y})");
+                    overlapping += 1;
+                }
+            }
+            assert!(overlapping > 0);
+        }
+    }
+
+    #[test]
+    fn test_drawer_mixed_symbol_sizes() {
+        let assets = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let dir = std::env::temp_dir().join(format!("sar-core-draw-mixed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["symbols_g.png", "symbols_b.png", "symbols_color.png"] {
+            std::fs::copy(assets.join(file), dir.join(file)).unwrap();
+        }
+        // Only the sheet of symbol 17 gets twice the resolution
+        let sheet = image::open(assets.join("symbols_r.png")).unwrap();
+        sheet
+            .resize(
+                sheet.width() * 2,
+                sheet.height() * 2,
+                imageops::FilterType::Nearest,
+            )
+            .save(dir.join("symbols_r.png"))
+            .unwrap();
+        let resource = resource::Resource::from_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let color = Color::new(255, 255, 0, 0);
+        let sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((100, 100), (150, 150), 17, color),
+            MockLayer::new((60, 60), (110, 110), 300, color),
+        ]);
+        let bundled = SymbolArtDrawer::default().draw(&sa).unwrap();
+        let image = SymbolArtDrawer::with_resource(resource).draw(&sa).unwrap();
+
+        // Sampling the finer sheet only moves the edges of the symbols by a pixel
+        let differing = image
+            .pixels()
+            .zip(bundled.pixels())
+            .filter(|(a, b)| a != b)
+            .count();
+        let drawn = bundled.pixels().filter(|p| p[3] > 0).count();
+        assert!(
+            differing * 20 < drawn,
+            "{differing} of {drawn} pixels differ"
+        );
+    }
+
+    #[test]
+    fn test_drawer_montage() {
+        let art = |symbol_id| {
+            MockSymbolArt::with_layers(vec![MockLayer::new(
+                (100, 100),
+                (150, 150),
+                symbol_id,
+                Color::new(255, 255, 0, 0),
+            )])
+        };
+        let arts = [art(17), art(18), art(19)];
+
+        let drawer = SymbolArtDrawer::default();
+        let sheet = drawer.montage(&arts, 2, (64, 32), 4).unwrap();
+        assert_eq!(sheet.dimensions(), (132, 68));
+        for (i, sa) in arts.iter().enumerate() {
+            let (x, y) = (i as u32 % 2 * 68, i as u32 / 2 * 36);
+            assert_eq!(
+                sheet.view(x, y, 64, 32).to_image(),
+                drawer.draw_fit(sa, 64, 32).unwrap()
+            );
+        }
+        // The gutters and the cell after the last art stay transparent
+        assert!(sheet.view(64, 0, 4, 68).pixels().all(|(_, _, p)| p[3] == 0));
+        assert!(sheet
+            .view(68, 36, 64, 32)
+            .pixels()
+            .all(|(_, _, p)| p[3] == 0));
+
+        assert!(matches!(
+            drawer.montage(&arts, 0, (64, 32), 4),
+            Err(SARError::InvalidCanvasSize(0, _))
+        ));
+        let empty = drawer
+            .montage::<MockSymbolArt, _>(&[], 3, (64, 32), 4)
+            .unwrap();
+        assert_eq!(empty.dimensions(), (200, 0));
+    }
+
+    #[test]
+    fn test_drawer_premultiplied_alpha() {
+        let mut layer = MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 255, 0, 0));
+        layer.alpha = 0.5;
+        let sa = MockSymbolArt::with_layers(vec![layer]);
+
+        let drawer = SymbolArtDrawer::default();
+        let straight = drawer.draw(&sa).unwrap();
+        let premultiplied = drawer.with_premultiplied_alpha(true).draw(&sa).unwrap();
+
+        let (x, y) = straight
+            .enumerate_pixels()
+            .find(|(_, _, p)| p[3] > 0)
+            .map(|(x, y, _)| (x, y))
+            .unwrap();
+        assert_eq!(*straight.get_pixel(x, y), Rgba([255, 0, 0, 128]));
+        assert_eq!(*premultiplied.get_pixel(x, y), Rgba([128, 0, 0, 128]));
+        // Transparent pixels have no color left
+        assert!(premultiplied
+            .pixels()
+            .filter(|p| p[3] == 0)
+            .all(|p| *p == Rgba([0; 4])));
+    }
+
+    #[test]
+    fn test_drawer_clone() {
+        let sa = MockSymbolArt::with_layers(vec![MockLayer::new(
+            (100, 100),
+            (150, 150),
+            17,
+            Color::new(255, 255, 0, 0),
+        )]);
+
+        let drawer = SymbolArtDrawer::default();
+        let expected = drawer.draw_cached(&sa, 1.0).unwrap();
+        let blue = drawer
+            .clone()
+            .with_background(Rgba([0, 0, 255, 255]))
+            .draw_cached(&sa, 1.0)
+            .unwrap();
+        // The clone draws with its own configuration rather than the cached image
+        assert_ne!(blue, expected);
+        assert_eq!(blue.get_pixel(0, 0), &Rgba([0, 0, 255, 255]));
+        assert_eq!(drawer.draw(&sa).unwrap(), *expected);
+
+        std::thread::scope(|s| {
+            for _ in 0..2 {
+                let drawer = drawer.clone();
+                let (sa, expected) = (&sa, &expected);
+                s.spawn(move || assert_eq!(drawer.draw(sa).unwrap(), **expected));
+            }
+        });
+    }
+
+    #[test]
+    fn test_drawer_draw_cached() {
+        let art = |symbol_id| {
+            MockSymbolArt::with_layers(vec![MockLayer::new(
+                (100, 100),
+                (150, 150),
+                symbol_id,
+                Color::new(255, 255, 0, 0),
+            )])
+        };
+        let (a, b) = (art(17), art(18));
+
+        let drawer = SymbolArtDrawer::default().with_cache_capacity(1);
+        let first = drawer.draw_cached(&a, 1.0).unwrap();
+        assert_eq!(*first, drawer.draw(&a).unwrap());
+        // A copy of the art hits the cache
+        assert!(Arc::ptr_eq(
+            &first,
+            &drawer.draw_cached(&a.clone(), 1.0).unwrap()
+        ));
+        assert!(!Arc::ptr_eq(&first, &drawer.draw_cached(&a, 2.0).unwrap()));
+        // The capacity is one, so drawing at 2.0 evicted the first image
+        assert!(!Arc::ptr_eq(&first, &drawer.draw_cached(&a, 1.0).unwrap()));
+        assert_eq!(
+            *drawer.draw_cached(&b, 1.0).unwrap(),
+            drawer.draw(&b).unwrap()
+        );
+
+        // Drawing `a` again makes `b` the least recently used one
+        let drawer = drawer.with_cache_capacity(2);
+        let first = drawer.draw_cached(&a, 1.0).unwrap();
+        let second = drawer.draw_cached(&b, 1.0).unwrap();
+        drawer.draw_cached(&a, 1.0).unwrap();
+        drawer.draw_cached(&art(19), 1.0).unwrap();
+        assert!(Arc::ptr_eq(&first, &drawer.draw_cached(&a, 1.0).unwrap()));
+        assert!(!Arc::ptr_eq(&second, &drawer.draw_cached(&b, 1.0).unwrap()));
+
+        let uncached = SymbolArtDrawer::default().with_cache_capacity(0);
+        let first = uncached.draw_cached(&a, 1.0).unwrap();
+        assert!(!Arc::ptr_eq(
+            &first,
+            &uncached.draw_cached(&a, 1.0).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_drawer_with_chunk_size_auto() {
+        let threads = rayon::current_num_threads();
+        for count in [1usize, 3, 40, 225] {
+            let sa = MockSymbolArt::with_layers(
+                (0..count)
+                    .map(|i| {
+                        let (x, y) = ((40 + i % 100) as u8, (90 + i % 50) as u8);
+                        let color = Color::new(255, (i * 31 % 256) as u8, 0, 255);
+                        MockLayer::new((x, y), (x + 30, y + 30), 17, color)
+                    })
+                    .collect(),
+            );
+
+            let drawer = SymbolArtDrawer::default().with_chunk_size_auto();
+            let chunk_size = count.div_ceil(threads).max(MIN_AUTO_CHUNK_SIZE);
+            let frames = drawer.draw_staged(&sa, 1.0).unwrap();
+            assert_eq!(frames.len(), count.div_ceil(chunk_size));
+
+            // Opaque layers composite the same whatever the chunks
+            let fixed = SymbolArtDrawer::default().draw(&sa).unwrap();
+            assert_eq!(*frames.last().unwrap(), fixed);
+        }
+    }
+
+    /// Compares drawing with chunks of 10 layers against `with_chunk_size_auto`
+    ///
+    /// Run with `cargo test --release -p sar-core bench_chunk_size_auto -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_chunk_size_auto() {
+        use std::time::Instant;
+
+        const ROUNDS: u32 = 10;
+        let fixed = SymbolArtDrawer::default().with_chunk_size(10);
+        let auto = SymbolArtDrawer::default().with_chunk_size_auto();
+        for count in [5usize, 20, 60, 120, 225] {
+            let sa = MockSymbolArt::with_layers(
+                (0..count)
+                    .map(|i| {
+                        let (x, y) = ((i * 7 % 150) as u8, (i * 13 % 150) as u8);
+                        let color = Color::new(255, (i * 31 % 256) as u8, 0, 128);
+                        MockLayer::new((x, y), (x + 60, y + 60), 17 + (i % 60) as u32, color)
+                    })
+                    .collect(),
+            );
+
+            let time = |drawer: &SymbolArtDrawer| {
+                let start = Instant::now();
+                for _ in 0..ROUNDS {
+                    drawer.draw(&sa).unwrap();
+                }
+                start.elapsed() / ROUNDS
+            };
+            let (fixed, auto) = (time(&fixed), time(&auto));
+
+            println!(
+                "{count} layers on {} threads: fixed {fixed:?}, auto {auto:?} ({:.2}x)",
+                rayon::current_num_threads(),
+                fixed.as_secs_f64() / auto.as_secs_f64()
+            );
+        }
+    }
+
+    #[test]
+    fn test_drawer_with_single_threaded() {
+        let sa = crate::parse(RAW_FILE.to_vec()).unwrap();
+        let parallel = SymbolArtDrawer::default().with_interpolation(Interpolation::Bilinear);
+        let sequential = SymbolArtDrawer::default()
+            .with_interpolation(Interpolation::Bilinear)
+            .with_single_threaded(true);
+        assert_eq!(
+            sequential.draw_with_scale(&sa, 0.5).unwrap(),
+            parallel.draw_with_scale(&sa, 0.5).unwrap()
+        );
+
+        let (image, errors) = sequential.draw_collect_errors(&sa);
+        assert!(errors.is_empty());
+        assert_eq!(image, parallel.draw(&sa).unwrap());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_drawer_tracing_spans() {
+        use std::sync::Mutex;
+
+        use tracing::{span, subscriber::Subscriber, Event, Metadata};
+
+        /// Records the name of every span
+        #[derive(Default)]
+        struct Recorder {
+            names: Mutex<Vec<&'static str>>,
+        }
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+                let mut names = self.names.lock().unwrap();
+                names.push(attrs.metadata().name());
+                span::Id::from_u64(names.len() as u64)
+            }
+
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((100, 100), (150, 150), 17, Color::new(255, 255, 0, 0)),
+            MockLayer::new((120, 120), (170, 170), 17, Color::new(255, 0, 0, 255)),
+        ]);
+        // The subscriber is only set on this thread
+        let drawer = SymbolArtDrawer::default()
+            .with_chunk_size(1)
+            .with_single_threaded(true);
+        let recorder = Arc::new(Recorder::default());
+        tracing::subscriber::with_default(recorder.clone(), || drawer.draw(&sa).unwrap());
+
+        let names = recorder.names.lock().unwrap();
+        let count = |name| names.iter().filter(|n| **n == name).count();
+        assert_eq!(names.first(), Some(&"draw"));
+        assert_eq!(count("chunk"), 2);
+        assert_eq!(count("projection"), 2);
+        assert_eq!(count("warp"), 2);
+        assert_eq!(names.last(), Some(&"composite"));
+    }
+
+    #[test]
+    fn test_drawer_with_layer_order() {
+        let red = Color::new(255, 255, 0, 0);
+        let blue = Color::new(255, 0, 0, 255);
+        let sa = MockSymbolArt::with_layers(vec![
+            MockLayer::new((100, 100), (150, 150), 17, red),
+            MockLayer::new((120, 120), (170, 170), 17, blue),
+        ]);
+
+        for chunk_size in [1, 10] {
+            let drawer = SymbolArtDrawer::default().with_chunk_size(chunk_size);
+            let top_first = drawer.draw(&sa).unwrap();
+            let bottom_first = drawer
+                .with_layer_order(LayerOrder::BottomFirst)
+                .draw(&sa)
+                .unwrap();
+
+            // The overlap takes the color of the other layer once the order flips
+            let overlap = top_first
+                .pixels()
+                .zip(bottom_first.pixels())
+                .filter(|(a, b)| a != b)
+                .collect::<Vec<_>>();
+            assert!(!overlap.is_empty());
+            assert!(overlap
+                .iter()
+                .all(|(a, b)| a.0 == [255, 0, 0, 255] && b.0 == [0, 0, 255, 255]));
+        }
+    }
+
+    #[test]
+    fn test_drawer_include_hidden() {
+        let mut sa = MockSymbolArt::with_layers(vec![MockLayer::new(
+            (100, 100),
+            (150, 150),
+            17,
+            Color::new(255, 255, 0, 0),
+        )]);
+        let shown = SymbolArtDrawer::default().draw(&sa).unwrap();
+        sa.layers[0].is_hidden = true;
+
+        let drawer = SymbolArtDrawer::default();
+        assert!(drawer.draw(&sa).unwrap().pixels().all(|p| p.0[3] == 0));
+        let (_, report) = drawer.draw_with_report(&sa, 1.0).unwrap();
+        assert_eq!(report.hidden, 1);
+
+        let drawer = drawer.with_include_hidden(true);
+        assert_eq!(drawer.draw(&sa).unwrap().as_raw(), shown.as_raw());
+        let (_, report) = drawer.draw_with_report(&sa, 1.0).unwrap();
+        assert_eq!(report.hidden, 0);
+
+        let faded = drawer.with_hidden_alpha(0.5).draw(&sa).unwrap();
+        let max_alpha = |image: &RgbaImage| image.pixels().map(|p| p.0[3]).max().unwrap();
+        assert_eq!(max_alpha(&shown), 255);
+        assert!((126..=129).contains(&max_alpha(&faded)));
     }
 }
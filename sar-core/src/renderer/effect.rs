@@ -0,0 +1,471 @@
+use std::collections::HashSet;
+
+use image::{imageops, imageops::FilterType, Pixel, Rgba, RgbaImage};
+
+use crate::core::sa::BlendMode;
+
+/// Finds the smallest rectangle containing every pixel that differs from `background`
+///
+/// Returns `(x, y, width, height)`, or `None` if nothing was drawn.
+pub(crate) fn drawn_bounds(
+    image: &RgbaImage,
+    background: Rgba<u8>,
+) -> Option<(u32, u32, u32, u32)> {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if *pixel == background {
+            continue;
+        }
+
+        bounds = Some(match bounds {
+            Some((min_x, min_y, max_x, max_y)) => {
+                (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+            }
+            None => (x, y, x, y),
+        });
+    }
+
+    bounds.map(|(min_x, min_y, max_x, max_y)| (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+}
+
+/// Surrounds the image with a border of `padding` pixels filled with `color`
+pub(crate) fn pad(image: &RgbaImage, padding: u32, color: Rgba<u8>) -> RgbaImage {
+    let mut padded = RgbaImage::from_pixel(
+        image.width() + padding * 2,
+        image.height() + padding * 2,
+        color,
+    );
+    imageops::replace(&mut padded, image, padding as i64, padding as i64);
+    padded
+}
+
+/// Replaces the color of every pixel with its luminance, keeping the alpha
+pub(crate) fn grayscale(image: &mut RgbaImage) {
+    for pixel in image.pixels_mut() {
+        let luma = pixel.to_luma()[0];
+        *pixel = Rgba([luma, luma, luma, pixel[3]]);
+    }
+}
+
+/// Multiplies the color channels of every pixel by the tint, keeping the alpha
+///
+/// Each channel is treated as a factor between 0.0 and 1.0, so white leaves
+/// the image unchanged. The alpha of the tint is ignored.
+pub(crate) fn tint(image: &mut RgbaImage, tint: Rgba<u8>) {
+    for pixel in image.pixels_mut() {
+        for channel in 0..3 {
+            pixel[channel] = (pixel[channel] as u16 * tint[channel] as u16 / 255) as u8;
+        }
+    }
+}
+
+/// The colors of the cells of `checkerboard`, the top-left one being the first
+pub(crate) const CHECKERBOARD_COLORS: [Rgba<u8>; 2] =
+    [Rgba([204, 204, 204, 255]), Rgba([153, 153, 153, 255])];
+
+/// Composites the image over a gray checkerboard of `cell` pixel squares, making it opaque
+///
+/// `origin` is the position of the image in the checkerboard, so the cells of
+/// images cut from a larger one line up.
+pub(crate) fn checkerboard(image: &mut RgbaImage, cell: u32, origin: (u32, u32)) {
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let parity = ((origin.0 + x) / cell + (origin.1 + y) / cell) % 2;
+        let mut backdrop = CHECKERBOARD_COLORS[parity as usize];
+        backdrop.blend(pixel);
+        *pixel = backdrop;
+    }
+}
+
+/// How many pixels NeuQuant skips between the ones it learns from in `quantize`
+const QUANTIZE_SAMPLE_FACTOR: i32 = 10;
+
+/// Reduces the image to at most `max_colors` distinct colors with NeuQuant
+///
+/// Images with few enough colors are left unchanged. Otherwise, unless only
+/// one color is allowed, the fully transparent pixels take one of the colors
+/// as transparent black, and the palette of the others is learnt from them
+/// alone, their alpha included. The result only depends on the image.
+pub(crate) fn quantize(image: &mut RgbaImage, max_colors: usize) {
+    let mut colors = HashSet::new();
+    let few_enough = image.pixels().all(|pixel| {
+        colors.insert(*pixel);
+        colors.len() <= max_colors
+    });
+    if few_enough {
+        return;
+    }
+
+    let reserve_transparent = max_colors > 1 && image.pixels().any(|pixel| pixel[3] == 0);
+    let is_learnt = |pixel: &Rgba<u8>| !reserve_transparent || pixel[3] > 0;
+    let samples = image
+        .pixels()
+        .filter(|pixel| is_learnt(pixel))
+        .flat_map(|pixel| pixel.0)
+        .collect::<Vec<_>>();
+    let palette_size = max_colors - reserve_transparent as usize;
+    let quantizer = color_quant::NeuQuant::new(QUANTIZE_SAMPLE_FACTOR, palette_size, &samples);
+
+    for pixel in image.pixels_mut() {
+        if is_learnt(pixel) {
+            quantizer.map_pixel(&mut pixel.0);
+        } else {
+            *pixel = Rgba([0; 4]);
+        }
+    }
+}
+
+/// Multiplies the color channels of the pixel by its alpha, rounding to the nearest
+pub(crate) fn premultiply(pixel: &mut Rgba<u8>) {
+    let alpha = pixel[3] as u16;
+    for channel in 0..3 {
+        pixel[channel] = ((pixel[channel] as u16 * alpha + 127) / 255) as u8;
+    }
+}
+
+/// Divides the color channels of the pixel by its alpha, undoing `premultiply`
+pub(crate) fn unpremultiply(pixel: &mut Rgba<u8>) {
+    let alpha = pixel[3] as u16;
+    if alpha == 0 {
+        return;
+    }
+    for channel in 0..3 {
+        pixel[channel] = ((pixel[channel] as u16 * 255 + alpha / 2) / alpha).min(255) as u8;
+    }
+}
+
+/// Shrinks the image to `width` x `height` with a Lanczos3 filter
+///
+/// The pixels are filtered in premultiplied alpha, so the colors of transparent
+/// pixels don't bleed into the edges of what is drawn.
+pub(crate) fn downsample(image: &RgbaImage, width: u32, height: u32) -> RgbaImage {
+    let mut premultiplied = image.clone();
+    premultiplied.pixels_mut().for_each(premultiply);
+    let mut resized = imageops::resize(&premultiplied, width, height, FilterType::Lanczos3);
+    resized.pixels_mut().for_each(unpremultiply);
+    resized
+}
+
+/// Spreads the colors of the drawn pixels into the transparent pixels around them
+///
+/// Each pass gives the transparent pixels next to a colored one the average
+/// color of those neighbors, their alpha staying zero. Sampling between pixels
+/// then blends the edges with a matching color rather than transparent black,
+/// which would show as a dark halo in straight alpha.
+pub(crate) fn bleed(image: &mut RgbaImage, passes: u32) {
+    let (width, height) = image.dimensions();
+    let mut colored: Vec<bool> = image.pixels().map(|pixel| pixel[3] > 0).collect();
+    for _ in 0..passes {
+        let mut filled = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                if colored[(y * width + x) as usize] {
+                    continue;
+                }
+
+                let (mut sum, mut count) = ([0u32; 3], 0);
+                for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                    for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                        if colored[(ny * width + nx) as usize] {
+                            let neighbor = image.get_pixel(nx, ny);
+                            for channel in 0..3 {
+                                sum[channel] += neighbor[channel] as u32;
+                            }
+                            count += 1;
+                        }
+                    }
+                }
+                if count > 0 {
+                    filled.push((x, y, sum.map(|channel| (channel / count) as u8)));
+                }
+            }
+        }
+
+        for (x, y, [r, g, b]) in filled {
+            image.put_pixel(x, y, Rgba([r, g, b, 0]));
+            colored[(y * width + x) as usize] = true;
+        }
+    }
+}
+
+/// The color of the pixels that differ in the output of `diff`
+const DIFF_HIGHLIGHT: Rgba<u8> = Rgba([255, 0, 255, 255]);
+
+/// How much of their alpha the identical pixels keep in the output of `diff`
+const DIFF_DIM: f32 = 0.25;
+
+/// Compares two images, highlighting the pixels that differ and dimming the others
+///
+/// The images are aligned on their top-left corner, and the result covers both.
+/// Pixels that only one of them has count as different unless they are
+/// transparent, like the missing ones.
+pub(crate) fn diff(a: &RgbaImage, b: &RgbaImage) -> RgbaImage {
+    let width = a.width().max(b.width());
+    let height = a.height().max(b.height());
+    let transparent = Rgba([0; 4]);
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let pa = a.get_pixel_checked(x, y).copied().unwrap_or(transparent);
+        let pb = b.get_pixel_checked(x, y).copied().unwrap_or(transparent);
+        if pa != pb {
+            return DIFF_HIGHLIGHT;
+        }
+
+        let mut pixel = pa;
+        pixel[3] = (pixel[3] as f32 * DIFF_DIM).round() as u8;
+        pixel
+    })
+}
+
+/// Blends `source` over `base` with the given mode, in straight alpha
+///
+/// `BlendMode::Normal` is the same as `Pixel::blend`. The other modes mix the
+/// colors as in the W3C compositing spec, weighted by the alpha of `base`,
+/// before compositing the result over `base`.
+pub(crate) fn blend(base: &mut Rgba<u8>, source: &Rgba<u8>, mode: BlendMode) {
+    let mix: fn(f32, f32) -> f32 = match mode {
+        BlendMode::Normal => return base.blend(source),
+        BlendMode::Multiply => |b, s| b * s,
+        BlendMode::Screen => |b, s| b + s - b * s,
+        BlendMode::Additive => |b, s| (b + s).min(1.0),
+    };
+
+    let source_alpha = source[3] as f32 / 255.0;
+    if source_alpha == 0.0 {
+        return;
+    }
+    let base_alpha = base[3] as f32 / 255.0;
+    let alpha = source_alpha + base_alpha * (1.0 - source_alpha);
+
+    for c in 0..3 {
+        let (b, s) = (base[c] as f32 / 255.0, source[c] as f32 / 255.0);
+        let mixed = (1.0 - base_alpha) * s + base_alpha * mix(b, s);
+        let color = (source_alpha * mixed + base_alpha * b * (1.0 - source_alpha)) / alpha;
+        base[c] = (color.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+    base[3] = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downsample() {
+        let mut image = RgbaImage::new(8, 8);
+        for y in 0..8 {
+            for x in 0..4 {
+                image.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            }
+        }
+
+        let small = downsample(&image, 2, 2);
+        assert_eq!(small.dimensions(), (2, 2));
+        for pixel in small.pixels().filter(|p| p[3] > 0) {
+            // Filtering in straight alpha would darken the red with the transparent black
+            assert_eq!(pixel.0[..3], [255, 0, 0]);
+        }
+        assert!(small.get_pixel(0, 1)[3] > small.get_pixel(1, 1)[3]);
+
+        let mut pixel = Rgba([100, 50, 0, 128]);
+        unpremultiply(&mut pixel);
+        assert_eq!(pixel, Rgba([199, 100, 0, 128]));
+    }
+
+    #[test]
+    fn test_diff() {
+        let red = Rgba([255, 0, 0, 200]);
+        let a = RgbaImage::from_pixel(2, 2, red);
+        let mut b = RgbaImage::from_pixel(3, 1, red);
+        b.put_pixel(1, 0, Rgba([0, 0, 255, 255]));
+
+        let diff = diff(&a, &b);
+        assert_eq!(diff.dimensions(), (3, 2));
+        assert_eq!(*diff.get_pixel(0, 0), Rgba([255, 0, 0, 50]));
+        assert_eq!(*diff.get_pixel(1, 0), DIFF_HIGHLIGHT);
+        // Outside one of the images
+        assert_eq!(*diff.get_pixel(2, 0), DIFF_HIGHLIGHT);
+        assert_eq!(*diff.get_pixel(0, 1), DIFF_HIGHLIGHT);
+        assert_eq!(*diff.get_pixel(2, 1), Rgba([0; 4]));
+    }
+
+    #[test]
+    fn test_bleed() {
+        let mut image = RgbaImage::new(5, 1);
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        image.put_pixel(2, 0, Rgba([0, 0, 255, 128]));
+        bleed(&mut image, 2);
+
+        assert_eq!(
+            image.pixels().copied().collect::<Vec<_>>(),
+            vec![
+                Rgba([255, 0, 0, 255]),
+                Rgba([127, 0, 127, 0]),
+                Rgba([0, 0, 255, 128]),
+                Rgba([0, 0, 255, 0]),
+                Rgba([0, 0, 255, 0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drawn_bounds() {
+        let mut image = RgbaImage::new(16, 16);
+        image.put_pixel(3, 4, Rgba([255, 0, 0, 255]));
+        image.put_pixel(10, 8, Rgba([0, 0, 255, 128]));
+
+        assert_eq!(drawn_bounds(&image, Rgba([0; 4])), Some((3, 4, 8, 5)));
+    }
+
+    #[test]
+    fn test_drawn_bounds_empty() {
+        let image = RgbaImage::from_pixel(16, 16, Rgba([255; 4]));
+
+        assert_eq!(drawn_bounds(&image, Rgba([255; 4])), None);
+    }
+
+    #[test]
+    fn test_pad() {
+        let image = RgbaImage::from_pixel(4, 2, Rgba([255; 4]));
+        let padded = pad(&image, 3, Rgba([0; 4]));
+
+        assert_eq!(padded.dimensions(), (10, 8));
+        assert_eq!(*padded.get_pixel(2, 2), Rgba([0; 4]));
+        assert_eq!(*padded.get_pixel(3, 3), Rgba([255; 4]));
+        assert_eq!(*padded.get_pixel(6, 4), Rgba([255; 4]));
+        assert_eq!(*padded.get_pixel(7, 5), Rgba([0; 4]));
+    }
+
+    #[test]
+    fn test_grayscale() {
+        let mut image = RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 128]));
+        grayscale(&mut image);
+
+        assert_eq!(*image.get_pixel(0, 0), Rgba([54, 54, 54, 128]));
+    }
+
+    #[test]
+    fn test_tint() {
+        let mut image = RgbaImage::from_pixel(1, 1, Rgba([255, 128, 64, 200]));
+        tint(&mut image, Rgba([255, 0, 128, 0]));
+
+        assert_eq!(*image.get_pixel(0, 0), Rgba([255, 0, 32, 200]));
+    }
+
+    #[test]
+    fn test_checkerboard() {
+        let [light, dark] = CHECKERBOARD_COLORS;
+        let mut image = RgbaImage::new(4, 4);
+        image.put_pixel(3, 3, Rgba([255, 0, 0, 255]));
+        checkerboard(&mut image, 2, (0, 0));
+
+        assert_eq!(*image.get_pixel(0, 0), light);
+        assert_eq!(*image.get_pixel(1, 1), light);
+        assert_eq!(*image.get_pixel(2, 0), dark);
+        assert_eq!(*image.get_pixel(0, 2), dark);
+        assert_eq!(*image.get_pixel(2, 2), light);
+        assert_eq!(*image.get_pixel(3, 3), Rgba([255, 0, 0, 255]));
+
+        let mut shifted = RgbaImage::new(2, 2);
+        checkerboard(&mut shifted, 2, (2, 0));
+        assert_eq!(*shifted.get_pixel(0, 0), dark);
+    }
+
+    #[test]
+    fn test_quantize() {
+        let gradient = RgbaImage::from_fn(64, 4, |x, y| match y {
+            0 => Rgba([0; 4]),
+            _ => Rgba([x as u8 * 4, 255 - x as u8 * 4, y as u8 * 60, 255]),
+        });
+        let count = |image: &RgbaImage| image.pixels().collect::<HashSet<_>>().len();
+
+        let mut image = gradient.clone();
+        quantize(&mut image, 8);
+        assert!(count(&image) <= 8, "{}", count(&image));
+        assert!(image
+            .rows()
+            .next()
+            .unwrap()
+            .all(|pixel| *pixel == Rgba([0; 4])));
+        assert!(image.pixels().skip(64).all(|pixel| pixel[3] == 255));
+
+        let mut again = gradient.clone();
+        quantize(&mut again, 8);
+        assert_eq!(again, image);
+
+        let mut single = gradient.clone();
+        quantize(&mut single, 1);
+        assert_eq!(count(&single), 1);
+
+        // Few enough colors already
+        let mut image = gradient.clone();
+        quantize(&mut image, 64 * 3 + 1);
+        assert_eq!(image, gradient);
+    }
+
+    #[test]
+    fn test_blend() {
+        let red = Rgba([255, 0, 0, 255]);
+        let blue = Rgba([0, 0, 255, 255]);
+        let blended = |base: Rgba<u8>, source: Rgba<u8>, mode| {
+            let mut pixel = base;
+            blend(&mut pixel, &source, mode);
+            pixel
+        };
+
+        assert_eq!(blended(blue, red, BlendMode::Normal), red);
+        assert_eq!(
+            blended(blue, red, BlendMode::Multiply),
+            Rgba([0, 0, 0, 255])
+        );
+        assert_eq!(
+            blended(blue, red, BlendMode::Screen),
+            Rgba([255, 0, 255, 255])
+        );
+        assert_eq!(
+            blended(blue, red, BlendMode::Additive),
+            Rgba([255, 0, 255, 255])
+        );
+
+        // A half transparent source is mixed with the base after blending
+        let half_red = Rgba([255, 0, 0, 128]);
+        assert_eq!(
+            blended(blue, half_red, BlendMode::Multiply),
+            Rgba([0, 0, 127, 255])
+        );
+        assert_eq!(
+            blended(blue, half_red, BlendMode::Screen),
+            Rgba([128, 0, 255, 255])
+        );
+
+        // Screen and additive tell apart on mid tones
+        let gray = Rgba([128, 128, 128, 255]);
+        assert_eq!(
+            blended(gray, gray, BlendMode::Multiply),
+            Rgba([64, 64, 64, 255])
+        );
+        assert_eq!(
+            blended(gray, gray, BlendMode::Screen),
+            Rgba([192, 192, 192, 255])
+        );
+        assert_eq!(
+            blended(gray, gray, BlendMode::Additive),
+            Rgba([255, 255, 255, 255])
+        );
+
+        // Nothing beneath leaves the source as is
+        for mode in [BlendMode::Multiply, BlendMode::Screen, BlendMode::Additive] {
+            assert_eq!(blended(Rgba([0; 4]), half_red, mode), half_red);
+        }
+    }
+
+    #[test]
+    fn test_premultiply() {
+        let mut pixel = Rgba([255, 128, 0, 128]);
+        premultiply(&mut pixel);
+        assert_eq!(pixel, Rgba([128, 64, 0, 128]));
+
+        let mut opaque = Rgba([200, 100, 50, 255]);
+        premultiply(&mut opaque);
+        assert_eq!(opaque, Rgba([200, 100, 50, 255]));
+    }
+}
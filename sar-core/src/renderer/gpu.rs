@@ -0,0 +1,514 @@
+use image::{Rgba, RgbaImage};
+use wgpu::util::DeviceExt;
+
+use super::resource::{self, Resource};
+
+/// The texture format of the rendered canvas
+const CANVAS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// A layer ready to be drawn by the GPU, in canvas pixel coordinates
+pub(crate) struct Quad {
+    /// The corners of the layer: top-left, top-right, bottom-right and bottom-left
+    pub(crate) corners: [(f32, f32); 4],
+    /// Which sheet the symbol comes from, and where it is on the sheet
+    pub(crate) sheet: u32,
+    pub(crate) origin: (u32, u32),
+    /// The size of the symbol, which depends on its sheet
+    pub(crate) symbol_pixels: u32,
+    /// The layer color, ignored for symbols of the color sheet
+    pub(crate) color: Rgba<u8>,
+    pub(crate) alpha: f32,
+}
+
+impl Quad {
+    /// Describes the symbol of `image` warped onto `corners`
+    pub(crate) fn new(
+        image: &resource::Image<'_>,
+        corners: [(f32, f32); 4],
+        color: Rgba<u8>,
+        alpha: f32,
+    ) -> Self {
+        let sheet = match image {
+            resource::Image::R(_) => 0,
+            resource::Image::G(_) => 1,
+            resource::Image::B(_) => 2,
+            resource::Image::Color(_) => 3,
+        };
+
+        Self {
+            corners,
+            sheet,
+            origin: image.inner().offsets(),
+            symbol_pixels: image.pixels(),
+            color,
+            alpha,
+        }
+    }
+}
+
+/// The per-layer vertex data, matching `Layer` in the shader
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Instance {
+    bounds: [f32; 4],
+    inverse: [[f32; 3]; 3],
+    origin: [u32; 2],
+    sheet: u32,
+    color: [f32; 4],
+    alpha: f32,
+    symbol_pixels: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    canvas_size: [f32; 2],
+    _padding: [f32; 2],
+}
+
+/// Renders layers with wgpu, with the symbol sheets uploaded once
+///
+/// The wgpu handles are reference counted, so clones share the device and the
+/// uploaded sheets.
+#[derive(Clone)]
+pub(crate) struct GpuRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sheets: [wgpu::TextureView; 4],
+}
+
+impl GpuRenderer {
+    /// Connects to the default adapter and uploads the sheets of the resource
+    ///
+    /// Returns `None` if no adapter is available.
+    pub(crate) fn new(resource: &Resource) -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+                .ok()?;
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).ok()?;
+
+        let sheets = resource
+            .sheets()
+            .map(|sheet| Self::upload(&device, &queue, &sheet.to_rgba8()));
+        let layout = Self::bind_group_layout(&device);
+        let pipeline = Self::pipeline(&device, &layout);
+
+        Some(Self {
+            device,
+            queue,
+            pipeline,
+            layout,
+            sheets,
+        })
+    }
+
+    /// Draws the quads from the bottom to the top onto a transparent canvas
+    ///
+    /// Returns `None` if the canvas is too large for the device.
+    pub(crate) fn draw(&self, quads: &[Quad], canvas_size: (u32, u32)) -> Option<RgbaImage> {
+        let (width, height) = canvas_size;
+        let max_size = self.device.limits().max_texture_dimension_2d;
+        if width > max_size || height > max_size {
+            return None;
+        }
+
+        let instances = quads
+            .iter()
+            .filter_map(|quad| self.instance(quad))
+            .collect::<Vec<_>>();
+        let instance_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("sar layers"),
+                contents: bytemuck::cast_slice(&instances),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+        let uniform_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("sar uniforms"),
+                contents: bytemuck::bytes_of(&Uniforms {
+                    canvas_size: [width as f32, height as f32],
+                    _padding: [0.0; 2],
+                }),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let bind_group = self.bind_group(&uniform_buffer);
+
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let canvas = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("sar canvas"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: CANVAS_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = canvas.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bytes_per_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sar readback"),
+            size: bytes_per_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("sar layers"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.set_vertex_buffer(0, instance_buffer.slice(..));
+            pass.draw(0..6, 0..instances.len() as u32);
+        }
+        encoder.copy_texture_to_buffer(
+            canvas.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            extent,
+        );
+        self.queue.submit([encoder.finish()]);
+
+        let slice = readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        self.device
+            .poll(wgpu::PollType::Wait {
+                submission_index: None,
+                timeout: None,
+            })
+            .ok()?;
+
+        let data = slice.get_mapped_range().ok()?;
+        let mut image = RgbaImage::new(width, height);
+        for (row, pixels) in data
+            .chunks_exact(bytes_per_row as usize)
+            .zip(image.chunks_exact_mut(width as usize * 4))
+        {
+            pixels.copy_from_slice(&row[..width as usize * 4]);
+        }
+        image.pixels_mut().for_each(unpremultiply);
+
+        Some(image)
+    }
+
+    /// Computes the bounding rectangle and the inverse mapping of the quad
+    ///
+    /// Returns `None` for degenerate quads, which can't cover any pixel.
+    fn instance(&self, quad: &Quad) -> Option<Instance> {
+        let size = quad.symbol_pixels as f64;
+        let inverse = invert(square_to_quad(quad.corners)?)?;
+        // Map the symbol pixels to the unit square before the inverse of the quad
+        let inverse = [
+            inverse[0].map(|v| v * size),
+            inverse[1].map(|v| v * size),
+            inverse[2],
+        ];
+
+        let (xs, ys) = (quad.corners.map(|c| c.0), quad.corners.map(|c| c.1));
+        let min = (
+            xs.into_iter().fold(f32::MAX, f32::min),
+            ys.into_iter().fold(f32::MAX, f32::min),
+        );
+        let max = (
+            xs.into_iter().fold(f32::MIN, f32::max),
+            ys.into_iter().fold(f32::MIN, f32::max),
+        );
+        // Nearest sampling reaches half a symbol pixel outside of the quad
+        let margin = (max.0 - min.0).max(max.1 - min.1) / quad.symbol_pixels as f32 + 1.0;
+        let bounds = [
+            min.0 - margin,
+            min.1 - margin,
+            max.0 + margin,
+            max.1 + margin,
+        ];
+
+        let Rgba([r, g, b, a]) = quad.color;
+        Some(Instance {
+            bounds,
+            inverse: inverse.map(|row| row.map(|v| v as f32)),
+            origin: [quad.origin.0, quad.origin.1],
+            sheet: quad.sheet,
+            color: [r, g, b, a].map(|c| c as f32 / 255.0),
+            alpha: quad.alpha,
+            symbol_pixels: quad.symbol_pixels as f32,
+        })
+    }
+
+    fn upload(device: &wgpu::Device, queue: &wgpu::Queue, sheet: &RgbaImage) -> wgpu::TextureView {
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("sar symbol sheet"),
+                size: wgpu::Extent3d {
+                    width: sheet.width(),
+                    height: sheet.height(),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            sheet.as_raw(),
+        );
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sar bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture(1),
+                texture(2),
+                texture(3),
+                texture(4),
+            ],
+        })
+    }
+
+    fn bind_group(&self, uniforms: &wgpu::Buffer) -> wgpu::BindGroup {
+        let [r, g, b, color] = &self.sheets;
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sar bind group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniforms.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(r),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(g),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(b),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(color),
+                },
+            ],
+        })
+    }
+
+    fn pipeline(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("gpu.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("sar pipeline layout"),
+            bind_group_layouts: &[Some(layout)],
+            ..Default::default()
+        });
+
+        let attributes = wgpu::vertex_attr_array![
+            0 => Float32x4,
+            1 => Float32x3,
+            2 => Float32x3,
+            3 => Float32x3,
+            4 => Uint32x2,
+            5 => Uint32,
+            6 => Float32x4,
+            7 => Float32,
+            8 => Float32,
+        ];
+        // Premultiplied alpha, so layers composite like `Rgba::blend`
+        let blend = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("sar pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[Some(wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Instance>() as u64,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &attributes,
+                })],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: CANVAS_FORMAT,
+                    blend: Some(wgpu::BlendState {
+                        color: blend,
+                        alpha: blend,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview_mask: None,
+            cache: None,
+        })
+    }
+}
+
+/// Converts a premultiplied pixel read back from the canvas to straight alpha
+fn unpremultiply(pixel: &mut Rgba<u8>) {
+    let alpha = pixel[3] as u32;
+    if alpha == 0 {
+        *pixel = Rgba([0; 4]);
+        return;
+    }
+    for channel in &mut pixel.0[..3] {
+        *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+    }
+}
+
+/// The homography mapping the unit square onto the quad, as rows of a 3x3 matrix
+///
+/// The corners are mapped from (0, 0), (1, 0), (1, 1) and (0, 1) in this order.
+fn square_to_quad(corners: [(f32, f32); 4]) -> Option<[[f64; 3]; 3]> {
+    let [(x0, y0), (x1, y1), (x2, y2), (x3, y3)] = corners.map(|(x, y)| (x as f64, y as f64));
+    let (dx1, dx2, dx3) = (x1 - x2, x3 - x2, x0 - x1 + x2 - x3);
+    let (dy1, dy2, dy3) = (y1 - y2, y3 - y2, y0 - y1 + y2 - y3);
+
+    let (g, h) = if dx3 == 0.0 && dy3 == 0.0 {
+        (0.0, 0.0)
+    } else {
+        let det = dx1 * dy2 - dx2 * dy1;
+        if det == 0.0 {
+            return None;
+        }
+        ((dx3 * dy2 - dx2 * dy3) / det, (dx1 * dy3 - dx3 * dy1) / det)
+    };
+
+    Some([
+        [x1 - x0 + g * x1, x3 - x0 + h * x3, x0],
+        [y1 - y0 + g * y1, y3 - y0 + h * y3, y0],
+        [g, h, 1.0],
+    ])
+}
+
+/// Inverts a 3x3 matrix, returning `None` if it is singular
+fn invert(m: [[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let cofactor =
+        |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let adjugate = [
+        [
+            cofactor(1, 2, 1, 2),
+            -cofactor(0, 2, 1, 2),
+            cofactor(0, 1, 1, 2),
+        ],
+        [
+            -cofactor(1, 2, 0, 2),
+            cofactor(0, 2, 0, 2),
+            -cofactor(0, 1, 0, 2),
+        ],
+        [
+            cofactor(1, 2, 0, 1),
+            -cofactor(0, 2, 0, 1),
+            cofactor(0, 1, 0, 1),
+        ],
+    ];
+    let det = m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+    if det.abs() < f64::EPSILON {
+        return None;
+    }
+
+    Some(adjugate.map(|row| row.map(|v| v / det)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square_to_quad() {
+        let corners = [(10.0, 20.0), (50.0, 25.0), (60.0, 70.0), (5.0, 60.0)];
+        let matrix = square_to_quad(corners).unwrap();
+        let inverse = invert(matrix).unwrap();
+
+        let map = |m: &[[f64; 3]; 3], (x, y): (f64, f64)| {
+            let [u, v, w] = m.map(|row| row[0] * x + row[1] * y + row[2]);
+            (u / w, v / w)
+        };
+        for (corner, square) in
+            corners
+                .into_iter()
+                .zip([(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)])
+        {
+            let (x, y) = map(&matrix, square);
+            assert!((x - corner.0 as f64).abs() < 1e-9 && (y - corner.1 as f64).abs() < 1e-9);
+
+            let (u, v) = map(&inverse, (corner.0 as f64, corner.1 as f64));
+            assert!((u - square.0).abs() < 1e-9 && (v - square.1).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_unpremultiply() {
+        let mut pixel = Rgba([64, 32, 0, 128]);
+        unpremultiply(&mut pixel);
+        assert_eq!(pixel, Rgba([128, 64, 0, 128]));
+    }
+}
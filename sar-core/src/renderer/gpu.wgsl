@@ -0,0 +1,98 @@
+// Draws every layer as an instanced rectangle covering its quad, and maps each
+// covered pixel back onto the symbol the same way `warp_into` does with
+// nearest-neighbor sampling.
+
+struct Uniforms {
+    canvas_size: vec2<f32>,
+}
+
+struct Layer {
+    @location(0) bounds: vec4<f32>,
+    @location(1) inverse_0: vec3<f32>,
+    @location(2) inverse_1: vec3<f32>,
+    @location(3) inverse_2: vec3<f32>,
+    @location(4) origin: vec2<u32>,
+    @location(5) sheet: u32,
+    @location(6) color: vec4<f32>,
+    @location(7) alpha: f32,
+    @location(8) symbol_pixels: f32,
+}
+
+struct Fragment {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) inverse_0: vec3<f32>,
+    @location(1) @interpolate(flat) inverse_1: vec3<f32>,
+    @location(2) @interpolate(flat) inverse_2: vec3<f32>,
+    @location(3) @interpolate(flat) origin: vec2<u32>,
+    @location(4) @interpolate(flat) sheet: u32,
+    @location(5) @interpolate(flat) color: vec4<f32>,
+    @location(6) @interpolate(flat) alpha: f32,
+    @location(7) @interpolate(flat) symbol_pixels: f32,
+}
+
+const SHEET_COLOR: u32 = 3u;
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var sheet_r: texture_2d<f32>;
+@group(0) @binding(2) var sheet_g: texture_2d<f32>;
+@group(0) @binding(3) var sheet_b: texture_2d<f32>;
+@group(0) @binding(4) var sheet_color: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, layer: Layer) -> Fragment {
+    var corners = array<vec2<f32>, 6>(
+        vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 1.0),
+        vec2(0.0, 1.0), vec2(1.0, 0.0), vec2(1.0, 1.0),
+    );
+    let pixel = mix(layer.bounds.xy, layer.bounds.zw, corners[index]);
+    let clip = vec2(pixel.x / uniforms.canvas_size.x * 2.0 - 1.0, 1.0 - pixel.y / uniforms.canvas_size.y * 2.0);
+
+    var out: Fragment;
+    out.position = vec4(clip, 0.0, 1.0);
+    out.inverse_0 = layer.inverse_0;
+    out.inverse_1 = layer.inverse_1;
+    out.inverse_2 = layer.inverse_2;
+    out.origin = layer.origin;
+    out.sheet = layer.sheet;
+    out.color = layer.color;
+    out.alpha = layer.alpha;
+    out.symbol_pixels = layer.symbol_pixels;
+    return out;
+}
+
+fn load(sheet: u32, coords: vec2<i32>) -> vec4<f32> {
+    switch sheet {
+        case 0u: { return textureLoad(sheet_r, coords, 0); }
+        case 1u: { return textureLoad(sheet_g, coords, 0); }
+        case 2u: { return textureLoad(sheet_b, coords, 0); }
+        default: { return textureLoad(sheet_color, coords, 0); }
+    }
+}
+
+@fragment
+fn fs_main(in: Fragment) -> @location(0) vec4<f32> {
+    // Map the integer pixel coordinates, like `warp_into` does
+    let pixel = vec3(floor(in.position.xy), 1.0);
+    let mapped = vec3(dot(in.inverse_0, pixel), dot(in.inverse_1, pixel), dot(in.inverse_2, pixel));
+    let uv = mapped.xy / mapped.z;
+
+    // Written so that NaN coordinates are discarded as well
+    let limit = in.symbol_pixels - 0.5;
+    if !(uv.x >= -0.5 && uv.y >= -0.5 && uv.x < limit && uv.y < limit) {
+        discard;
+    }
+
+    let texel = load(in.sheet, vec2<i32>(in.origin + vec2<u32>(uv + 0.5)));
+    if texel.a == 0.0 {
+        discard;
+    }
+
+    var source = in.color;
+    if in.sheet == SHEET_COLOR {
+        source = texel;
+    }
+    let alpha = round(source.a * 255.0 * in.alpha) / 255.0;
+
+    // The target is blended with premultiplied alpha
+    return vec4(source.rgb * alpha, alpha);
+}
@@ -1,6 +1,17 @@
+mod cache;
 pub mod draw;
+mod effect;
+#[cfg(feature = "gpu")]
+mod gpu;
 pub mod resource;
-pub use draw::SymbolArtDrawer;
+mod scratch;
+#[cfg(feature = "tokio")]
+mod stream;
+pub use draw::{ChannelOrder, LayerOrder, LayerTransform, RenderReport, SymbolArtDrawer};
+pub use imageproc::geometric_transformations::Interpolation;
+pub use imageproc::rect::Rect;
+#[cfg(feature = "tokio")]
+pub use stream::render_stream;
 
 pub(crate) mod default {
     use super::draw::{Drawer, SymbolArtDrawer};
@@ -1,15 +1,33 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, OnceLock},
+};
 
-use crate::core::result::Result;
-use image::{imageops, DynamicImage, SubImage};
+use super::effect;
+use crate::core::result::{Result, SARError};
+use image::{imageops, DynamicImage, GenericImageView, RgbaImage, SubImage};
 
-use crate::core::symbol::SymbolId;
+use crate::core::symbol::{is_known_symbol, SymbolId, MAX_SYMBOL_ID};
 
 const SYMBOLS_R: &[u8] = include_bytes!("../../assets/symbols_r.png");
 const SYMBOLS_G: &[u8] = include_bytes!("../../assets/symbols_g.png");
 const SYMBOLS_B: &[u8] = include_bytes!("../../assets/symbols_b.png");
 const SYMBOLS_COLOR: &[u8] = include_bytes!("../../assets/symbols_color.png");
+const SYMBOL_NAMES: &str = include_str!("../../assets/symbol_names.tsv");
 
+/// The file name of the optional symbol names read by `Resource::from_dir`
+const NAMES_FILE: &str = "symbol_names.tsv";
+
+/// The file names of the image sheets expected by `Resource::from_dir`
+const SHEET_FILES: [(ImageSheet, &str); 4] = [
+    (ImageSheet::R, "symbols_r.png"),
+    (ImageSheet::G, "symbols_g.png"),
+    (ImageSheet::B, "symbols_b.png"),
+    (ImageSheet::Color, "symbols_color.png"),
+];
+
+#[cfg(test)]
 const SYMBOL_PIXELS: u32 = 64;
 const SYMBOL_WIDTH_NUM: u32 = 16;
 
@@ -25,52 +43,225 @@ enum ImageSheet {
 ///
 /// This struct manages multiple image sheets (R, G, B, and Color) that contain
 /// symbols used for rendering. Each sheet is stored as a DynamicImage and can be
-/// accessed to extract individual symbol images.
+/// accessed to extract individual symbol images. The size of the symbols is
+/// derived from the width of their sheet, so sheets of different resolutions
+/// can be mixed. Clones share the sheets and the converted symbols.
+#[derive(Clone)]
 pub struct Resource {
     /// A map of image sheets, where each sheet contains a collection of symbols
-    sheets: HashMap<ImageSheet, DynamicImage>,
-    /// The size of each symbol in pixels (width and height are equal)
-    pub symbol_pixels: u32,
+    sheets: Arc<HashMap<ImageSheet, DynamicImage>>,
+    /// The symbols converted to RGBA by `get_rgba_image`, indexed by id
+    rgba_images: Arc<[OnceLock<RgbaImage>]>,
+    /// The names returned by `name_of`, by id
+    names: Arc<HashMap<u32, String>>,
 }
 
 impl Resource {
+    /// Loads the symbols bundled with the crate
     pub fn new() -> Result<Self> {
-        let r = image::load_from_memory(SYMBOLS_R)?;
-        let g = image::load_from_memory(SYMBOLS_G)?;
-        let b = image::load_from_memory(SYMBOLS_B)?;
-        let color = image::load_from_memory(SYMBOLS_COLOR)?;
+        let mut sheets = HashMap::new();
+        sheets.insert(ImageSheet::R, image::load_from_memory(SYMBOLS_R)?);
+        sheets.insert(ImageSheet::G, image::load_from_memory(SYMBOLS_G)?);
+        sheets.insert(ImageSheet::B, image::load_from_memory(SYMBOLS_B)?);
+        sheets.insert(ImageSheet::Color, image::load_from_memory(SYMBOLS_COLOR)?);
 
+        Self::from_sheets(sheets, Self::parse_names(SYMBOL_NAMES)?)
+    }
+
+    /// Loads a custom symbol pack from a directory
+    ///
+    /// The directory must contain the same four image sheets as the bundled pack:
+    /// - `symbols_r.png`: symbol ids 1 to 80
+    /// - `symbols_g.png`: symbol ids 240 to 480
+    /// - `symbols_b.png`: symbol ids 481 to 720
+    /// - `symbols_color.png`: symbol ids 721 to 768, drawn with their own colors
+    ///
+    /// Each sheet is a grid of square symbols, 16 symbols wide, laid out in the
+    /// same order as the bundled sheets. The symbol size is derived from the sheet
+    /// width, so a 2048 pixels wide sheet holds 128x128 symbols. The sheets don't
+    /// need to have the same width.
+    ///
+    /// The names of the symbols are read from `symbol_names.tsv` if the directory
+    /// has one, in the format of the bundled `assets/symbol_names.tsv`: a symbol
+    /// id and its name separated by a tab on each line, `#` starting a comment.
+    pub fn from_dir(path: &Path) -> Result<Self> {
         let mut sheets = HashMap::new();
-        sheets.insert(ImageSheet::R, r);
-        sheets.insert(ImageSheet::G, g);
-        sheets.insert(ImageSheet::B, b);
-        sheets.insert(ImageSheet::Color, color);
+        for (sheet, file) in SHEET_FILES {
+            sheets.insert(sheet, image::open(path.join(file))?);
+        }
+
+        let names = match std::fs::read_to_string(path.join(NAMES_FILE)) {
+            Ok(text) => Self::parse_names(&text)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Self::from_sheets(sheets, names)
+    }
+
+    /// Parses the lines of a symbol names file into names by id
+    fn parse_names(text: &str) -> Result<HashMap<u32, String>> {
+        let mut names = HashMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let entry = line
+                .split_once('\t')
+                .and_then(|(id, name)| Some((id.trim().parse::<u32>().ok()?, name.trim())));
+            match entry {
+                Some((id, name)) if !name.is_empty() => {
+                    names.insert(id, name.to_string());
+                }
+                _ => {
+                    return Err(SARError::InvalidResource(format!(
+                        "line {} of {NAMES_FILE} is not an id and a name separated by a tab",
+                        number + 1
+                    )))
+                }
+            }
+        }
+
+        Ok(names)
+    }
+
+    fn from_sheets(
+        sheets: HashMap<ImageSheet, DynamicImage>,
+        names: HashMap<u32, String>,
+    ) -> Result<Self> {
+        for sheet in sheets.values() {
+            let width = sheet.width();
+            if width == 0 || !width.is_multiple_of(SYMBOL_WIDTH_NUM) {
+                return Err(SARError::InvalidResource(format!(
+                    "sheet width {width} is not a multiple of {SYMBOL_WIDTH_NUM}"
+                )));
+            }
+        }
 
         Ok(Self {
-            sheets,
-            symbol_pixels: SYMBOL_PIXELS,
+            sheets: Arc::new(sheets),
+            rgba_images: (0..=MAX_SYMBOL_ID).map(|_| OnceLock::new()).collect(),
+            names: Arc::new(names),
         })
     }
 
-    pub(crate) fn get_image(&self, id: SymbolId) -> Option<Image> {
+    /// Returns the human readable name of the symbol for the given id
+    ///
+    /// Returns `None` if the symbol isn't named, which includes ids that don't
+    /// belong to any sheet.
+    pub fn name_of(&self, id: SymbolId) -> Option<&str> {
+        self.names.get(&id.id()).map(String::as_str)
+    }
+
+    /// Returns the size in pixels of the symbol for the given id, which is square
+    ///
+    /// Returns `None` if the id doesn't belong to any sheet.
+    pub fn symbol_pixels(&self, id: SymbolId) -> Option<u32> {
+        let index = ImageIndex::get(id)?;
+        self.sheets.get(&index.sheet).map(Self::sheet_symbol_pixels)
+    }
+
+    fn sheet_symbol_pixels(sheet: &DynamicImage) -> u32 {
+        sheet.width() / SYMBOL_WIDTH_NUM
+    }
+
+    /// Returns the number of symbols available in this resource
+    pub fn symbol_count(&self) -> usize {
+        (0..=MAX_SYMBOL_ID)
+            .filter(|id| self.contains(SymbolId::new(*id)))
+            .count()
+    }
+
+    /// Returns whether a symbol exists for the given id, without extracting its image
+    pub fn contains(&self, id: SymbolId) -> bool {
+        let Some(index) = ImageIndex::get(id) else {
+            return false;
+        };
+        let Some(sheet) = self.sheets.get(&index.sheet) else {
+            return false;
+        };
+
+        let size = Self::sheet_symbol_pixels(sheet);
+        let (x, y) = Self::get_coordinates(&index, size);
+        x + size <= sheet.width() && y + size <= sheet.height()
+    }
+
+    /// Whether the symbol for the given id is drawn with its own colors
+    ///
+    /// Only the symbols of the Color sheet are, ids that don't belong to any
+    /// sheet being treated like textured symbols.
+    pub(crate) fn is_colored(id: SymbolId) -> bool {
+        ImageIndex::get(id).is_some_and(|index| index.sheet == ImageSheet::Color)
+    }
+
+    /// The image sheets in the order R, G, B and Color
+    #[cfg(feature = "gpu")]
+    pub(crate) fn sheets(&self) -> [&DynamicImage; 4] {
+        [
+            ImageSheet::R,
+            ImageSheet::G,
+            ImageSheet::B,
+            ImageSheet::Color,
+        ]
+        .map(|sheet| &self.sheets[&sheet])
+    }
+
+    /// Returns the symbol for the given id, cut out of its sheet
+    ///
+    /// Returns `None` if the id doesn't belong to any sheet. The image borrows the
+    /// sheet, use `Image::to_rgba` to save or compare it.
+    pub fn get_image(&self, id: SymbolId) -> Option<Image<'_>> {
         let index = ImageIndex::get(id)?;
         let sheet = self.sheets.get(&index.sheet)?;
-        let (x, y) = Self::get_coordinates(&index);
-        let image = imageops::crop_imm(sheet, x, y, SYMBOL_PIXELS, SYMBOL_PIXELS);
+        let size = Self::sheet_symbol_pixels(sheet);
+        let (x, y) = Self::get_coordinates(&index, size);
+        let image = imageops::crop_imm(sheet, x, y, size, size);
         Some(Image::new_with_sheet(image, index))
     }
 
-    fn get_coordinates(index: &ImageIndex) -> (u32, u32) {
-        let x = index.index % SYMBOL_WIDTH_NUM * SYMBOL_PIXELS;
-        let y = index.index / SYMBOL_WIDTH_NUM * SYMBOL_PIXELS;
+    /// The symbol for the given id as an RGBA image
+    ///
+    /// Each symbol is converted on its first use only, so layers reusing a
+    /// symbol share the conversion. The transparent pixels of the symbols drawn
+    /// with their own colors take the color of the pixels next to them, so
+    /// `Interpolation::Bilinear` and `Bicubic` don't darken their edges. Only the
+    /// alpha of the other symbols is used.
+    pub(crate) fn get_rgba_image(&self, id: SymbolId) -> Option<&RgbaImage> {
+        let rgba_image = self.rgba_images.get(id.id() as usize)?;
+        let image = self.get_image(id)?;
+        Some(rgba_image.get_or_init(|| {
+            let mut rgba_image = image.to_rgba();
+            if image.is_color_symbol() {
+                // Bicubic sampling reaches two pixels away
+                effect::bleed(&mut rgba_image, 2);
+            }
+            rgba_image
+        }))
+    }
+
+    fn get_coordinates(index: &ImageIndex, size: u32) -> (u32, u32) {
+        let x = index.index % SYMBOL_WIDTH_NUM * size;
+        let y = index.index / SYMBOL_WIDTH_NUM * size;
         (x, y)
     }
 }
 
-pub(crate) enum Image<'a> {
+/// A symbol cut out of the sheet it belongs to
+///
+/// The sheet decides how the layer color is applied. The symbols of the `R`, `G`
+/// and `B` sheets are textured: only their alpha is used, as the shape that is
+/// filled with the layer color. The symbols of the `Color` sheet are drawn with
+/// the colors of their own pixels, and the layer color is ignored.
+pub enum Image<'a> {
+    /// A textured symbol with an id from 1 to 80
     R(SubImage<&'a DynamicImage>),
+    /// A textured symbol with an id from 240 to 480
     G(SubImage<&'a DynamicImage>),
+    /// A textured symbol with an id from 481 to 720
     B(SubImage<&'a DynamicImage>),
+    /// A symbol drawn with its own colors, with an id from 721 to 768
     Color(SubImage<&'a DynamicImage>),
 }
 
@@ -83,6 +274,27 @@ impl<'a> Image<'a> {
             Image::Color(image) => image,
         }
     }
+
+    /// Whether the symbol is drawn with the colors of its own pixels
+    ///
+    /// That's the case for the symbols of the Color sheet only. The layer color
+    /// is ignored for them, see `SymbolArtLayer::effective_color`.
+    pub fn is_color_symbol(&self) -> bool {
+        matches!(self, Image::Color(_))
+    }
+
+    /// The size of the symbol in pixels, its width and height being equal
+    pub fn pixels(&self) -> u32 {
+        self.inner().width()
+    }
+
+    /// Copies the pixels of the symbol as they are in the sheet
+    ///
+    /// Textured symbols keep the colors of the sheet, which the renderer
+    /// replaces with the layer color.
+    pub fn to_rgba(&self) -> RgbaImage {
+        self.inner().to_image()
+    }
 }
 
 impl std::fmt::Debug for Image<'_> {
@@ -114,6 +326,10 @@ struct ImageIndex {
 
 impl ImageIndex {
     fn get(id: SymbolId) -> Option<Self> {
+        if !is_known_symbol(id.id()) {
+            return None;
+        }
+
         match id.id() {
             id @ 1..=80 => Some(Self {
                 sheet: ImageSheet::R,
@@ -155,6 +371,7 @@ mod test {
     use image::GenericImageView;
 
     use super::*;
+    use crate::{test::MockLayer, Color, SymbolArtLayer};
 
     #[test]
     fn test_get_image_index() {
@@ -178,4 +395,203 @@ mod test {
             panic!("image2 is not R");
         }
     }
+
+    #[test]
+    fn test_to_rgba() {
+        let resource = Resource::new().unwrap();
+        let image = resource.get_image(SymbolId::new(721)).unwrap();
+        let rgba = image.to_rgba();
+
+        assert!(matches!(image, Image::Color(_)));
+        assert_eq!(rgba.dimensions(), (image.pixels(), image.pixels()));
+        assert!(rgba.pixels().any(|p| p[3] > 0));
+    }
+
+    #[test]
+    fn test_effective_color() {
+        let resource = Resource::new().unwrap();
+        let red = Color::new(255, 255, 0, 0);
+        let layer = |id| MockLayer::new((0, 0), (10, 10), id, red);
+
+        let textured = resource.get_image(SymbolId::new(40)).unwrap();
+        assert!(!textured.is_color_symbol());
+        assert_eq!(layer(40).effective_color(&textured), Some(red));
+
+        let colored = resource.get_image(SymbolId::new(721)).unwrap();
+        assert!(colored.is_color_symbol());
+        assert_eq!(layer(721).effective_color(&colored), None);
+    }
+
+    #[test]
+    fn test_contains() {
+        let resource = Resource::new().unwrap();
+
+        assert!(resource.contains(SymbolId::new(1)));
+        assert!(resource.contains(SymbolId::new(721)));
+        assert!(!resource.contains(SymbolId::new(0)));
+        assert!(!resource.contains(SymbolId::new(769)));
+        for id in 0..=MAX_SYMBOL_ID {
+            let id = SymbolId::new(id);
+            assert_eq!(
+                resource.contains(id),
+                resource.get_image(id).is_some_and(|image| {
+                    image.inner().width() == SYMBOL_PIXELS
+                        && image.inner().height() == SYMBOL_PIXELS
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_rgba_image() {
+        let resource = Resource::new().unwrap();
+        let id = SymbolId::new(40);
+        let image = resource.get_rgba_image(id).unwrap();
+
+        assert_eq!(image, &resource.get_image(id).unwrap().to_rgba());
+        assert!(std::ptr::eq(image, resource.get_rgba_image(id).unwrap()));
+        // Clones share the converted symbols
+        assert!(std::ptr::eq(
+            image,
+            resource.clone().get_rgba_image(id).unwrap()
+        ));
+        assert!(resource.get_rgba_image(SymbolId::new(0)).is_none());
+        assert!(resource.get_rgba_image(SymbolId::new(769)).is_none());
+    }
+
+    #[test]
+    fn test_symbol_count() {
+        let resource = Resource::new().unwrap();
+        let count = (0..=MAX_SYMBOL_ID)
+            .filter(|id| resource.contains(SymbolId::new(*id)))
+            .count();
+
+        assert_eq!(resource.symbol_count(), count);
+        assert!(resource.symbol_count() > 0);
+    }
+
+    #[test]
+    fn test_from_dir() {
+        let dir = std::env::temp_dir().join(format!("sar-core-resource-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let upscaled = |bytes: &[u8]| {
+            let sheet = image::load_from_memory(bytes).unwrap();
+            sheet.resize(
+                sheet.width() * 2,
+                sheet.height() * 2,
+                imageops::FilterType::Nearest,
+            )
+        };
+        for ((_, file), bytes) in
+            SHEET_FILES
+                .iter()
+                .zip([SYMBOLS_R, SYMBOLS_G, SYMBOLS_B, SYMBOLS_COLOR])
+        {
+            upscaled(bytes).save(dir.join(file)).unwrap();
+        }
+
+        let resource = Resource::from_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let bundled = Resource::new().unwrap();
+        assert_eq!(
+            resource.symbol_pixels(SymbolId::new(40)),
+            Some(SYMBOL_PIXELS * 2)
+        );
+        assert_eq!(resource.symbol_count(), bundled.symbol_count());
+        let image = resource.get_image(SymbolId::new(40)).unwrap();
+        assert_eq!(image.inner().width(), SYMBOL_PIXELS * 2);
+    }
+
+    #[test]
+    fn test_from_dir_mixed_sizes() {
+        let dir =
+            std::env::temp_dir().join(format!("sar-core-resource-mixed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for ((sheet, file), bytes) in
+            SHEET_FILES
+                .iter()
+                .zip([SYMBOLS_R, SYMBOLS_G, SYMBOLS_B, SYMBOLS_COLOR])
+        {
+            let mut image = image::load_from_memory(bytes).unwrap();
+            if *sheet == ImageSheet::R {
+                image = image.resize(
+                    image.width() * 2,
+                    image.height() * 2,
+                    imageops::FilterType::Nearest,
+                );
+            }
+            image.save(dir.join(file)).unwrap();
+        }
+        std::fs::write(
+            dir.join(NAMES_FILE),
+            "# names\n40\tstar\n\n300\tbig heart\n",
+        )
+        .unwrap();
+
+        let resource = Resource::from_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(resource.name_of(SymbolId::new(40)), Some("star"));
+        assert_eq!(resource.name_of(SymbolId::new(300)), Some("big heart"));
+        assert_eq!(resource.name_of(SymbolId::new(41)), None);
+        assert_eq!(
+            resource.symbol_pixels(SymbolId::new(40)),
+            Some(SYMBOL_PIXELS * 2)
+        );
+        assert_eq!(
+            resource.symbol_pixels(SymbolId::new(300)),
+            Some(SYMBOL_PIXELS)
+        );
+        assert_eq!(resource.symbol_pixels(SymbolId::new(0)), None);
+        let image = resource.get_image(SymbolId::new(300)).unwrap();
+        assert_eq!(image.pixels(), SYMBOL_PIXELS);
+        assert_eq!(
+            resource.symbol_count(),
+            Resource::new().unwrap().symbol_count()
+        );
+    }
+
+    #[test]
+    fn test_parse_names() {
+        let names = Resource::parse_names("# comment\n\n17\tcircle \n 18 \t square\n").unwrap();
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[&17], "circle");
+        assert_eq!(names[&18], "square");
+
+        for text in ["17 circle", "x\tcircle", "17\t"] {
+            let e = Resource::parse_names(text).unwrap_err();
+            assert!(matches!(e, SARError::InvalidResource(_)), "{text:?}");
+        }
+
+        // The bundled table parses, and unknown ids have no name
+        let resource = Resource::new().unwrap();
+        assert_eq!(resource.name_of(SymbolId::new(0)), None);
+        assert_eq!(resource.name_of(SymbolId::new(9999)), None);
+    }
+
+    #[test]
+    fn test_name_of() {
+        let resource = Resource::new().unwrap();
+        assert_eq!(resource.name_of(SymbolId::new(240)), Some("circle"));
+        assert_eq!(resource.name_of(SymbolId::new(251)), Some("star"));
+        assert_eq!(resource.name_of(SymbolId::new(254)), Some("heart"));
+        assert_eq!(resource.name_of(SymbolId::new(52)), Some("digit 0"));
+        // The two empty cells of the G sheet have no name
+        assert_eq!(resource.name_of(SymbolId::new(302)), None);
+        assert_eq!(resource.name_of(SymbolId::new(100)), None);
+
+        // Every named symbol has an image to go with it
+        assert!(resource
+            .names
+            .keys()
+            .all(|id| resource.contains(SymbolId::new(*id))));
+    }
+
+    #[test]
+    fn test_from_dir_missing() {
+        let dir = std::env::temp_dir().join("sar-core-resource-missing");
+
+        assert!(Resource::from_dir(&dir).is_err());
+    }
 }
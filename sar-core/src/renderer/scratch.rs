@@ -0,0 +1,57 @@
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use image::RgbaImage;
+
+/// A pool of canvas sized buffers the symbols are warped into
+///
+/// A buffer is taken out for the time a layer is drawn and then given back, so
+/// each thread drawing a chunk reuses the same few buffers instead of
+/// allocating one per layer. A thread-local buffer isn't enough, as rayon may
+/// run another chunk on the same thread while `warp_into` waits on its rows.
+#[derive(Default)]
+pub(crate) struct ScratchPool {
+    buffers: Mutex<Vec<RgbaImage>>,
+    #[cfg(test)]
+    allocations: AtomicUsize,
+}
+
+/// Clones start out with no buffers, which are per drawer
+impl Clone for ScratchPool {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl ScratchPool {
+    /// Takes a buffer of the given size out of the pool, allocating one if none fits
+    ///
+    /// The content of the buffer is left over from its previous use, so it must
+    /// be overwritten entirely.
+    pub(crate) fn take(&self, dimensions: (u32, u32)) -> RgbaImage {
+        let buffer = self.buffers.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        match buffer {
+            Some(buffer) if buffer.dimensions() == dimensions => buffer,
+            _ => {
+                #[cfg(test)]
+                self.allocations.fetch_add(1, Ordering::Relaxed);
+                RgbaImage::new(dimensions.0, dimensions.1)
+            }
+        }
+    }
+
+    /// Gives a buffer back to the pool
+    pub(crate) fn put(&self, buffer: RgbaImage) {
+        self.buffers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(buffer);
+    }
+
+    /// The number of buffers allocated by `take` so far
+    #[cfg(test)]
+    pub(crate) fn allocations(&self) -> usize {
+        self.allocations.load(Ordering::Relaxed)
+    }
+}
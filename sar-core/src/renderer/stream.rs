@@ -0,0 +1,108 @@
+use std::{
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use image::RgbaImage;
+use tokio::sync::{mpsc, Semaphore};
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
+
+use super::draw::{Drawer, SymbolArtDrawer};
+use crate::{parser::payload::parse_file_async, Result};
+
+/// Parses and draws the SAR files at `paths`, yielding each result as it completes
+///
+/// As many files as there are CPUs are rendered at once, and the next path is
+/// only taken from `paths` once one of them is done. The files are drawn with
+/// `Drawer::draw` on the blocking thread pool, so the results come in the order
+/// they finish rather than the order of `paths`. Dropping the returned stream
+/// stops taking new paths.
+///
+/// # Panics
+///
+/// Panics if called outside a tokio runtime.
+///
+/// ```no_run
+/// use std::{path::PathBuf, sync::Arc};
+///
+/// use sar_core::{render_stream, SymbolArtDrawer};
+/// use tokio_stream::StreamExt;
+///
+/// # async fn run() {
+/// let paths = tokio_stream::iter(vec![PathBuf::from("a.sar"), PathBuf::from("b.sar")]);
+/// let mut results = render_stream(paths, Arc::new(SymbolArtDrawer::new()));
+/// while let Some((path, image)) = results.next().await {
+///     println!("{}: {:?}", path.display(), image.map(|image| image.dimensions()));
+/// }
+/// # }
+/// ```
+pub fn render_stream<P>(
+    paths: P,
+    drawer: Arc<SymbolArtDrawer>,
+) -> impl Stream<Item = (PathBuf, Result<RgbaImage>)>
+where
+    P: Stream<Item = PathBuf> + Send + 'static,
+{
+    let jobs = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let (sender, receiver) = mpsc::channel(jobs);
+
+    tokio::spawn(async move {
+        let semaphore = Arc::new(Semaphore::new(jobs));
+        let mut paths = std::pin::pin!(paths);
+        while let Some(path) = paths.next().await {
+            let Ok(permit) = semaphore.clone().acquire_owned().await else {
+                break;
+            };
+            if sender.is_closed() {
+                break;
+            }
+
+            let (sender, drawer) = (sender.clone(), drawer.clone());
+            tokio::spawn(async move {
+                let result = render_file(&path, drawer).await;
+                // The receiver is gone when the stream was dropped
+                let _ = sender.send((path, result)).await;
+                drop(permit);
+            });
+        }
+    });
+
+    ReceiverStream::new(receiver)
+}
+
+async fn render_file(path: &Path, drawer: Arc<SymbolArtDrawer>) -> Result<RgbaImage> {
+    let payload = parse_file_async(path).await?;
+    tokio::task::spawn_blocking(move || drawer.draw(&payload))
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::result::SARError;
+
+    #[tokio::test]
+    async fn test_render_stream() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../fixture/sa0a1d081b8a108bb8c9847c4cd83db662.sar");
+        let missing = Path::new(env!("CARGO_MANIFEST_DIR")).join("missing.sar");
+        let paths = tokio_stream::iter(vec![fixture.clone(), missing.clone(), fixture.clone()]);
+
+        let mut results = render_stream(paths, Arc::new(SymbolArtDrawer::new()))
+            .collect::<Vec<_>>()
+            .await;
+        results.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        assert_eq!(results.len(), 3);
+        for (path, result) in results {
+            if path == missing {
+                assert!(matches!(result, Err(SARError::IoError(_))));
+            } else {
+                assert_eq!(path, fixture);
+                assert_eq!(result.unwrap().dimensions(), (193, 96));
+            }
+        }
+    }
+}
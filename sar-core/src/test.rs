@@ -1,3 +1,127 @@
+use crate::core::{
+    sa::{BlendMode, Color, Position, SymbolArt, SymbolArtLayer},
+    symbol::{Symbol, SymbolId},
+};
+
 pub const RAW_FILE: &[u8] = include_bytes!("../../fixture/sa0a1d081b8a108bb8c9847c4cd83db662.sar");
 pub const RAW_FILE_UNCOMPRESSED: &[u8] =
     include_bytes!("../../fixture/sa0da01caf059ccf36f36c5ee4136544c5.sar");
+
+/// A SymbolArt built in memory, for tests that need specific dimensions or layers
+#[derive(Debug, Clone)]
+pub struct MockSymbolArt {
+    pub width: u8,
+    pub height: u8,
+    pub layers: Vec<MockLayer>,
+}
+
+impl MockSymbolArt {
+    /// A 193x96 SymbolArt like most files hold, made of `layers`
+    pub fn with_layers(layers: Vec<MockLayer>) -> Self {
+        Self {
+            width: 193,
+            height: 96,
+            layers,
+        }
+    }
+}
+
+impl SymbolArt for MockSymbolArt {
+    type Layer = MockLayer;
+
+    fn author_id(&self) -> u32 {
+        0
+    }
+
+    fn height(&self) -> u8 {
+        self.height
+    }
+
+    fn width(&self) -> u8 {
+        self.width
+    }
+
+    fn layers(&self) -> Vec<Self::Layer> {
+        self.layers.clone()
+    }
+
+    fn name(&self) -> &str {
+        ""
+    }
+}
+
+/// A layer of a `MockSymbolArt`, covering an axis-aligned square
+#[derive(Debug, Clone)]
+pub struct MockLayer {
+    pub top_left: Position,
+    pub bottom_right: Position,
+    pub symbol_id: u32,
+    pub color: Color,
+    pub is_hidden: bool,
+    pub alpha: f32,
+    pub blend_mode: BlendMode,
+}
+
+impl MockLayer {
+    pub fn new(top_left: (u8, u8), bottom_right: (u8, u8), symbol_id: u32, color: Color) -> Self {
+        Self {
+            top_left: Position {
+                x: top_left.0,
+                y: top_left.1,
+            },
+            bottom_right: Position {
+                x: bottom_right.0,
+                y: bottom_right.1,
+            },
+            symbol_id,
+            color,
+            is_hidden: false,
+            alpha: 1.0,
+            blend_mode: BlendMode::Normal,
+        }
+    }
+}
+
+impl SymbolArtLayer for MockLayer {
+    fn top_left(&self) -> Position {
+        self.top_left
+    }
+
+    fn bottom_left(&self) -> Position {
+        Position {
+            x: self.top_left.x,
+            y: self.bottom_right.y,
+        }
+    }
+
+    fn top_right(&self) -> Position {
+        Position {
+            x: self.bottom_right.x,
+            y: self.top_left.y,
+        }
+    }
+
+    fn bottom_right(&self) -> Position {
+        self.bottom_right
+    }
+
+    fn symbol(&self) -> Symbol {
+        Symbol::new(SymbolId::new(self.symbol_id))
+    }
+
+    fn color(&self) -> Color {
+        self.color
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.is_hidden
+    }
+
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+}
@@ -0,0 +1,64 @@
+//! Helpers to test code that renders SymbolArts
+//!
+//! Encoders may compress the same pixels differently from one version to the
+//! next, so renders are compared by their decoded pixels rather than by bytes.
+
+use image::RgbaImage;
+
+/// Asserts that two images have the same dimensions and that no channel of a
+/// pixel differs by more than `max_per_channel_diff`
+///
+/// # Panics
+///
+/// Panics if the dimensions differ, or with the first pixel that differs by
+/// more and how many pixels do.
+///
+/// ```
+/// use image::{Rgba, RgbaImage};
+/// use sar_core::testing::assert_images_close;
+///
+/// let a = RgbaImage::from_pixel(2, 2, Rgba([100, 100, 100, 255]));
+/// let b = RgbaImage::from_pixel(2, 2, Rgba([101, 99, 100, 255]));
+/// assert_images_close(&a, &b, 1);
+/// ```
+#[track_caller]
+pub fn assert_images_close(a: &RgbaImage, b: &RgbaImage, max_per_channel_diff: u8) {
+    assert_eq!(a.dimensions(), b.dimensions(), "the dimensions differ");
+
+    let differs = |(a, b): &(&image::Rgba<u8>, &image::Rgba<u8>)| {
+        a.0.iter()
+            .zip(b.0)
+            .any(|(a, b)| a.abs_diff(b) > max_per_channel_diff)
+    };
+    let mut pixels = a
+        .enumerate_pixels()
+        .zip(b.pixels())
+        .map(|((x, y, a), b)| ((x, y), (a, b)));
+    if let Some(((x, y), (pa, pb))) = pixels.find(|(_, pair)| differs(pair)) {
+        let count = 1 + pixels.filter(|(_, pair)| differs(pair)).count();
+        panic!(
+            "{count} pixels differ by more than {max_per_channel_diff}, the first at ({x}, {y}): {:?} != {:?}",
+            pa.0, pb.0
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "2 pixels differ by more than 1, the first at (1, 0)")]
+    fn test_assert_images_close() {
+        let a = RgbaImage::from_pixel(2, 2, Rgba([100, 100, 100, 255]));
+        let mut b = a.clone();
+        b.put_pixel(0, 0, Rgba([101, 100, 100, 255]));
+        assert_images_close(&a, &b, 1);
+
+        b.put_pixel(1, 0, Rgba([100, 100, 100, 253]));
+        b.put_pixel(0, 1, Rgba([0, 100, 100, 255]));
+        assert_images_close(&a, &b, 1);
+    }
+}
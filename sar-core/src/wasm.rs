@@ -0,0 +1,93 @@
+//! Bindings to parse and draw SymbolArts from JavaScript
+//!
+//! The symbol sheets are embedded in the binary, so nothing has to be fetched
+//! besides the SAR file itself. Browsers don't give WebAssembly threads by
+//! default, in which case rayon runs every chunk on the calling thread and the
+//! output is the same as with threads.
+
+use std::sync::OnceLock;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Payload, SymbolArt as _, SymbolArtDrawer};
+
+/// A parsed SymbolArt, handed out to JavaScript
+#[wasm_bindgen]
+pub struct JsSymbolArt {
+    payload: Payload,
+}
+
+#[wasm_bindgen]
+impl JsSymbolArt {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.payload.name().to_string()
+    }
+
+    #[wasm_bindgen(getter = authorId)]
+    pub fn author_id(&self) -> u32 {
+        self.payload.author_id()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u8 {
+        self.payload.width()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u8 {
+        self.payload.height()
+    }
+
+    /// The number of layers, hidden ones included
+    #[wasm_bindgen(getter = layerCount)]
+    pub fn layer_count(&self) -> usize {
+        self.payload.layers().len()
+    }
+
+    /// The number of layers that are drawn
+    #[wasm_bindgen(getter = visibleLayerCount)]
+    pub fn visible_layer_count(&self) -> usize {
+        self.payload.visible_layers().count()
+    }
+}
+
+/// Parses the content of a SAR file, throwing if it is malformed
+#[wasm_bindgen]
+pub fn parse(bytes: &[u8]) -> Result<JsSymbolArt, JsError> {
+    let payload = crate::parse(bytes.to_vec()).map_err(to_js_error)?;
+    Ok(JsSymbolArt { payload })
+}
+
+/// Draws the SymbolArt at the given scale and encodes it as PNG
+///
+/// The drawer and its symbol sheets are created on the first call and reused
+/// afterwards.
+#[wasm_bindgen]
+pub fn draw(sa: &JsSymbolArt, scale: f32) -> Result<Vec<u8>, JsError> {
+    static DRAWER: OnceLock<SymbolArtDrawer> = OnceLock::new();
+
+    let drawer = DRAWER.get_or_init(SymbolArtDrawer::new);
+    drawer.draw_png(&sa.payload, scale).map_err(to_js_error)
+}
+
+fn to_js_error(e: impl std::fmt::Display) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::RAW_FILE;
+
+    #[test]
+    fn test_parse_and_draw() {
+        let sa = parse(RAW_FILE).unwrap();
+        assert_eq!((sa.width(), sa.height()), (193, 96));
+        assert!(sa.visible_layer_count() <= sa.layer_count());
+
+        let png = draw(&sa, 1.0).unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (193, 96));
+    }
+}